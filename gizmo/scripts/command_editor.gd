@@ -3,6 +3,9 @@ class_name CommandEditor
 
 signal parameters_changed(command_id: int, command_arg_values: Array)
 
+# Arguments that only make sense between 0 and 1
+const UNIT_ARGS := ["amount", "factor"]
+
 var command_id: int
 var command_name: String
 var command_args: Dictionary
@@ -18,7 +21,7 @@ func _ready():
 		container.add_child(arg_name_label)
 		
 		var arg_value = command_args[arg_name]
-		var value_edit = ValueEdit.new(arg_value)
+		var value_edit = ValueEdit.new(arg_value, arg_name in UNIT_ARGS)
 		value_edit.set_h_size_flags(Control.SIZE_EXPAND_FILL)
 		value_edit.value_changed.connect(func (new_value: Variant):
 			command_args[arg_name] = new_value
@@ -15,6 +15,11 @@ func get_command_editor():
 	if command_editor is CommandEditor:
 		return command_editor
 
+# New commands are inserted at the finish line, which then sits right after them
+func add_command_editor(command_editor: CommandEditor):
+	add_child(command_editor)
+	move_child(command_editor, %FinishLine.get_index())
+
 func _input(event: InputEvent):
 	if not editable:
 		return
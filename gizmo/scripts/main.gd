@@ -2,7 +2,7 @@ extends Node
 
 
 func _process(delta: float):
-	var face_positions = Array(%Interpreter.mesh.get_face_positions(%Interpreter.selections.back()))
+	var face_positions = Array(%Interpreter.mesh.get_face_positions(%Interpreter.get_selections().back()))
 	%Camera3D.desired_position = face_positions.reduce(func(x, y): return x + y) / 3.0
 	
 	if Engine.get_frames_drawn() % 5 == 0:
@@ -13,6 +13,10 @@ func _input(event: InputEvent):
 		%CommandFileWriter.visible = true
 	elif event.is_action_pressed("load"):
 		%CommandFileReader.visible = true
+	elif event.is_action_pressed("export"):
+		%MeshFileWriter.visible = true
+	elif event.is_action_pressed("import"):
+		%MeshFileReader.visible = true
 	elif event.is_action_pressed("undo_command"):
 		var editor: CommandEditor = %CommandStackContainer.get_command_editor()
 		if editor != null:
@@ -27,11 +31,33 @@ func _input(event: InputEvent):
 	elif event.is_action_pressed("move_edge_selection"):
 		%Interpreter.move_edge_selection()
 	elif event.is_action_pressed("translate"):
-		%Interpreter.translate(Vector3(1, 0, 0))
+		%Interpreter.translate(Vector3(1, 0, 0), "world")
+	elif event.is_action_pressed("scale"):
+		%Interpreter.scale(Vector3(1.5, 1.5, 1.5), "centroid", "face")
+	elif event.is_action_pressed("rotate"):
+		%Interpreter.rotate(Vector3(0, 1, 0), 15.0, "centroid", "face")
 	elif event.is_action_pressed("split"):
 		%Interpreter.split(0.5)
+	elif event.is_action_pressed("poke"):
+		%Interpreter.poke(Vector3(1, 1, 1))
 	elif event.is_action_pressed("pull"):
 		%Interpreter.pull()
+	elif event.is_action_pressed("extrude"):
+		%Interpreter.extrude(0.5)
+	elif event.is_action_pressed("inset"):
+		%Interpreter.inset(0.25)
+	elif event.is_action_pressed("edge_collapse"):
+		%Interpreter.edge_collapse(0.5)
+	elif event.is_action_pressed("flip_edge"):
+		%Interpreter.flip_edge()
+	elif event.is_action_pressed("delete_face"):
+		%Interpreter.delete_face()
+	elif event.is_action_pressed("fill_hole"):
+		%Interpreter.fill_hole()
+	elif event.is_action_pressed("subdivide"):
+		%Interpreter.subdivide(1, "loop", "region")
+	elif event.is_action_pressed("smooth"):
+		%Interpreter.smooth(1, 0.5, "taubin", "region")
 	elif event.is_action_pressed("color"):
 		%Interpreter.color(Color(1.0, 1.0, 1.0))
 	
@@ -41,9 +67,25 @@ func _on_interpreter_command_executed(command_id:  int, command_name:  String, c
 	command_editor.command_name = command_name
 	command_editor.command_args = command_args
 	command_editor.parameters_changed.connect(%Interpreter.update_command)
-	%CommandStackContainer.add_child(command_editor)
+	%CommandStackContainer.add_command_editor(command_editor)
 
 
+# Shows the error over the model view for a few seconds, then fades it out
+func _on_interpreter_command_failed(command_id:  int, message:  String) -> void:
+	var label = Label.new()
+	if command_id == -1:
+		label.text = message
+	else:
+		label.text = "Command %d: %s" % [command_id, message]
+	$GUI/MarginContainer.add_child(label)
+	
+	var tween = get_tree().create_tween()
+	tween.tween_interval(2.0)
+	tween.tween_property(label, "modulate", Color(label.modulate.r, label.modulate.g, label.modulate.b, 0), 1.0)
+	tween.finished.connect(func():
+		label.queue_free()
+	)
+
 func _on_command_file_writer_file_selected(path:  String) -> void:
 	var file = FileAccess.open(path, FileAccess.WRITE)
 	if file:
@@ -65,5 +107,36 @@ func _on_command_file_reader_file_selected(path:  String) -> void:
 		push_error("Failed to load command stack from: %s", path)
 
 
-func _on_command_stack_container_finish_line_changed(id) -> void:
-	print(id)
+func _on_mesh_file_writer_file_selected(path:  String) -> void:
+	var exported := false
+	match path.get_extension().to_lower():
+		"gltf":
+			exported = %Interpreter.mesh.export_gltf(path)
+		"glb":
+			exported = %Interpreter.mesh.export_glb(path)
+		"stl":
+			for issue in %Interpreter.mesh.check_watertight():
+				push_warning("The mesh won't print cleanly, %s" % issue)
+			exported = %Interpreter.mesh.export_stl(path, false)
+		"ply":
+			exported = %Interpreter.mesh.export_ply(path, true)
+		_:
+			exported = %Interpreter.mesh.export_obj(path)
+	if exported:
+		print("Exported the mesh to: %s" % path)
+	else:
+		push_error("Failed to export the mesh to: %s" % path)
+
+
+func _on_mesh_file_reader_file_selected(path:  String) -> void:
+	if %Interpreter.import_mesh(path):
+		print("Imported a mesh from: %s" % path)
+	else:
+		push_error("Failed to import a mesh from: %s" % path)
+
+
+func _on_command_stack_container_finish_line_changed(command_editor) -> void:
+	if command_editor == null:
+		%Interpreter.set_finish_line_to_start()
+	else:
+		%Interpreter.set_finish_line(command_editor.command_id)
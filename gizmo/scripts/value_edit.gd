@@ -5,7 +5,7 @@ signal value_changed(new_value)
 
 var value
 
-func _init(initial_value: Variant) -> void:
+func _init(initial_value: Variant, unit_range := true) -> void:
 	var type = typeof(initial_value)
 	self.value = initial_value
 	match type:
@@ -22,6 +22,8 @@ func _init(initial_value: Variant) -> void:
 			spin_box.step = 0.01
 			spin_box.min_value = 0.0
 			spin_box.max_value = 1.0
+			spin_box.allow_lesser = not unit_range
+			spin_box.allow_greater = not unit_range
 			spin_box.rounded = false
 			spin_box.value = initial_value
 			spin_box.value_changed.connect(func(val):
@@ -31,10 +33,12 @@ func _init(initial_value: Variant) -> void:
 			add_child(spin_box)
 		TYPE_INT:
 			var spin_box := SpinBox.new()
+			spin_box.allow_greater = true
 			spin_box.value = value
+			# SpinBox values are floats, but the command wants a whole number back
 			spin_box.value_changed.connect(func(val):
-				value = val
-				value_changed.emit(val)
+				value = int(val)
+				value_changed.emit(value)
 			)
 			add_child(spin_box)
 		TYPE_VECTOR3:
@@ -0,0 +1,1176 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::CommandError;
+use crate::math::{Color, Vec3};
+use crate::mesh::{Mesh, MetaIndexId, decompose_meta_index, next_corner, previous_corner};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum Command {
+    // Selection stack manipulation
+    PushSelection,
+    PopSelection,
+
+    // Moving the selection around relatively
+    MoveFaceSelection,
+    MoveEdgeSelection,
+
+    // Model mutations
+    /// Moves the selected vertex by `delta`, measured along the axes of `space`.
+    Translate {
+        delta: Vec3,
+        space: Space,
+    },
+    /// Scales the target by `factor` along each axis, away from the pivot.
+    Scale {
+        factor: Vec3,
+        pivot: Pivot,
+        target: Target,
+    },
+    /// Turns the target `angle` degrees around `axis`, through the pivot. Positive angles turn
+    /// counter-clockwise when the axis points toward the viewer.
+    Rotate {
+        axis: Vec3,
+        angle: f32,
+        pivot: Pivot,
+        target: Target,
+    },
+    Split(f32),
+    /// Adds a vertex inside the selected face, weighted toward the selected vertex, the next one
+    /// and the one after by the barycentric coordinates, and splits the face into three around it.
+    /// The weights are scaled to add up to 1.
+    Poke(Vec3),
+    Pull,
+    Extrude(f32),
+    Inset(f32),
+    EdgeCollapse(f32),
+    FlipEdge,
+    DeleteFace,
+    FillHole,
+    /// Splits the target's faces into four, `levels` times over, along with just enough of the
+    /// faces around them to keep the surface joined up. Selections move onto the child face at the
+    /// corner they were on, still pointing along the same edge.
+    Subdivide {
+        levels: u32,
+        scheme: Subdivision,
+        target: Target,
+    },
+    /// Moves the target's vertices `factor` of the way toward the middle of their neighbours,
+    /// `iterations` times over. Vertices on a boundary only follow the boundary.
+    Smooth {
+        iterations: u32,
+        factor: f32,
+        mode: Smoothing,
+        target: Target,
+    },
+
+    // Vertex attributes
+    Color(Color),
+}
+
+/// The axes Translate measures its delta along.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Space {
+    #[default]
+    World,
+    /// x along the selected edge, z along the face normal and y across the face, so that a stack
+    /// keeps moving the same way relative to the face however earlier commands turned it.
+    Face,
+}
+
+/// Where Subdivide puts the vertices it makes.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Subdivision {
+    /// Loop subdivision, which smooths the surface by moving every vertex toward its neighbours.
+    #[default]
+    Loop,
+    /// New vertices go in the middle of each edge and old ones stay put, keeping the shape.
+    Midpoint,
+}
+
+/// How Smooth relaxes vertices.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Smoothing {
+    /// Plain Laplacian smoothing, which also shrinks the mesh.
+    #[default]
+    Laplacian,
+    /// Taubin smoothing, which follows every step with a slightly bigger one back out so the mesh
+    /// keeps its volume.
+    Taubin,
+}
+
+/// The point Scale and Rotate leave where it is.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Pivot {
+    /// The middle of the selected face.
+    #[default]
+    FaceCentroid,
+    /// The selected vertex.
+    Vertex,
+    Origin,
+    /// The middle of the box around the whole mesh.
+    BoundsCenter,
+}
+
+/// What Scale, Rotate and Smooth move, and what Subdivide splits.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Target {
+    /// The selected vertex, or for Subdivide the faces around it.
+    Vertex,
+    /// The three vertices of the selected face, or the face itself.
+    #[default]
+    Face,
+    /// Every vertex or face connected to the selected face.
+    Region,
+}
+
+impl Space {
+    pub const ALL: [Space; 2] = [Space::World, Space::Face];
+
+    /// The short name used in scripts and the editor.
+    pub fn name(self) -> &'static str {
+        match self {
+            Space::World => "world",
+            Space::Face => "face",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Space> {
+        Space::ALL.into_iter().find(|space| space.name() == name)
+    }
+}
+
+impl Subdivision {
+    pub const ALL: [Subdivision; 2] = [Subdivision::Loop, Subdivision::Midpoint];
+
+    /// The short name used in scripts and the editor.
+    pub fn name(self) -> &'static str {
+        match self {
+            Subdivision::Loop => "loop",
+            Subdivision::Midpoint => "midpoint",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Subdivision> {
+        Subdivision::ALL
+            .into_iter()
+            .find(|subdivision| subdivision.name() == name)
+    }
+}
+
+impl Smoothing {
+    pub const ALL: [Smoothing; 2] = [Smoothing::Laplacian, Smoothing::Taubin];
+
+    /// The short name used in scripts and the editor.
+    pub fn name(self) -> &'static str {
+        match self {
+            Smoothing::Laplacian => "laplacian",
+            Smoothing::Taubin => "taubin",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Smoothing> {
+        Smoothing::ALL
+            .into_iter()
+            .find(|smoothing| smoothing.name() == name)
+    }
+}
+
+impl Pivot {
+    pub const ALL: [Pivot; 4] = [
+        Pivot::FaceCentroid,
+        Pivot::Vertex,
+        Pivot::Origin,
+        Pivot::BoundsCenter,
+    ];
+
+    /// The short name used in scripts and the editor.
+    pub fn name(self) -> &'static str {
+        match self {
+            Pivot::FaceCentroid => "centroid",
+            Pivot::Vertex => "vertex",
+            Pivot::Origin => "origin",
+            Pivot::BoundsCenter => "bounds",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Pivot> {
+        Pivot::ALL.into_iter().find(|pivot| pivot.name() == name)
+    }
+}
+
+impl Target {
+    pub const ALL: [Target; 3] = [Target::Vertex, Target::Face, Target::Region];
+
+    /// The short name used in scripts and the editor.
+    pub fn name(self) -> &'static str {
+        match self {
+            Target::Vertex => "vertex",
+            Target::Face => "face",
+            Target::Region => "region",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Target> {
+        Target::ALL.into_iter().find(|target| target.name() == name)
+    }
+}
+
+impl Command {
+    /// Runs the command on the mesh. If it fails, neither the mesh nor the selections are changed.
+    pub fn call(
+        &self,
+        mesh: &mut Mesh,
+        selections: &mut Vec<MetaIndexId>,
+    ) -> Result<(), CommandError> {
+        let selection = *selections.last().ok_or(CommandError::NoSelection)?;
+        let meta_index = mesh.get_meta_index(selection)? as usize;
+        match self {
+            Command::PushSelection => selections.push(selection),
+            Command::PopSelection => {
+                if selections.len() == 1 {
+                    return Err(CommandError::PopLastSelection);
+                }
+                selections.pop();
+            }
+            Command::MoveFaceSelection => mesh.traverse_connection(selection)?,
+            Command::MoveEdgeSelection => {
+                let (start, offset) = decompose_meta_index(meta_index);
+                mesh.tracked_indices
+                    .insert(selection, (start + (offset + 1) % 3) as i32);
+            }
+            Command::Translate { delta, space } => {
+                let delta = match space {
+                    Space::World => *delta,
+                    Space::Face => {
+                        let [a, b, _] = mesh.get_face_positions(selection)?;
+                        let normal = mesh.face_normal(meta_index);
+                        let tangent = (b - a).normalized();
+                        let bitangent = normal.cross(tangent);
+                        tangent * delta.x + bitangent * delta.y + normal * delta.z
+                    }
+                };
+                mesh.modify_vertex(
+                    selection,
+                    mesh.positions[mesh.indices[meta_index] as usize] + delta,
+                )?;
+            }
+            Command::Scale {
+                factor,
+                pivot,
+                target,
+            } => {
+                if ![factor.x, factor.y, factor.z].iter().all(|c| c.is_finite()) {
+                    return Err(CommandError::ScaleFactorNotFinite(*factor));
+                }
+                let pivot = pivot_position(mesh, meta_index, *pivot);
+                transform(mesh, meta_index, *target, |position| {
+                    let offset = position - pivot;
+                    pivot
+                        + Vec3::new(
+                            offset.x * factor.x,
+                            offset.y * factor.y,
+                            offset.z * factor.z,
+                        )
+                })?;
+            }
+            Command::Rotate {
+                axis,
+                angle,
+                pivot,
+                target,
+            } => {
+                // An infinite axis would normalize to NaN and move every vertex nowhere
+                let finite = [axis.x, axis.y, axis.z, *angle]
+                    .iter()
+                    .all(|c| c.is_finite());
+                if !finite {
+                    return Err(CommandError::RotationNotFinite(*axis, *angle));
+                }
+                let axis = axis.normalized();
+                if axis == Vec3::ZERO {
+                    return Err(CommandError::ZeroRotationAxis);
+                }
+                let pivot = pivot_position(mesh, meta_index, *pivot);
+                let (sin, cos) = angle.to_radians().sin_cos();
+                transform(mesh, meta_index, *target, |position| {
+                    // Rodrigues' rotation formula
+                    let offset = position - pivot;
+                    pivot
+                        + offset * cos
+                        + axis.cross(offset) * sin
+                        + axis * (axis.dot(offset) * (1.0 - cos))
+                })?;
+            }
+            Command::Split(amount) => {
+                if !(0.0..=1.0).contains(amount) {
+                    return Err(CommandError::SplitAmountOutOfRange(*amount));
+                }
+
+                // Create new vertex
+                let (start_a, offset_a) = decompose_meta_index(meta_index);
+                let first_position = mesh.positions[mesh.indices[meta_index] as usize];
+                let second_position =
+                    mesh.positions[mesh.indices[next_corner(meta_index)] as usize];
+                let new_position = first_position.lerp(second_position, *amount);
+                let new_index = mesh.add_vertex(new_position);
+
+                // On a boundary there's only the one face to split
+                let Some(twin) = mesh.twin(meta_index) else {
+                    let face_a = &mesh.indices[start_a..start_a + 3];
+                    mesh.add_face(
+                        [
+                            new_index as i32,
+                            face_a[(offset_a + 1) % 3],
+                            face_a[(offset_a + 2) % 3],
+                        ],
+                        [
+                            Mesh::BOUNDARY,
+                            mesh.connections[start_a + (offset_a + 1) % 3],
+                            (start_a + (offset_a + 1) % 3) as i32,
+                        ],
+                    );
+                    mesh.indices[start_a + (offset_a + 1) % 3] = new_index as i32;
+                    return Ok(());
+                };
+
+                // Add two new faces
+                let (start_b, offset_b) = decompose_meta_index(twin);
+                let face_a = &mesh.indices[start_a..start_a + 3];
+                let face_b = &mesh.indices[start_b..start_b + 3];
+                let conn_a = &mesh.connections[start_a..start_a + 3];
+                let conn_b = &mesh.connections[start_b..start_b + 3];
+                let new_indices = [
+                    new_index as i32,
+                    face_a[(offset_a + 1) % 3],
+                    face_a[(offset_a + 2) % 3],
+                    new_index as i32,
+                    face_b[(offset_b + 1) % 3],
+                    face_b[(offset_b + 2) % 3],
+                ];
+                let new_connections = [
+                    (start_b + offset_b) as i32,
+                    conn_a[(offset_a + 1) % 3],
+                    (start_a + (offset_a + 1) % 3) as i32,
+                    meta_index as i32,
+                    conn_b[(offset_b + 1) % 3],
+                    (start_b + (offset_b + 1) % 3) as i32,
+                ];
+                mesh.add_faces(new_indices, new_connections);
+                // Update the old faces to use the new vertex
+                mesh.indices[start_a + (offset_a + 1) % 3] = new_index as i32;
+                mesh.indices[start_b + (offset_b + 1) % 3] = new_index as i32;
+            }
+            Command::Poke(weights) => {
+                // NaN or infinite weights would put the new vertex nowhere
+                let sum = weights.x + weights.y + weights.z;
+                let inside = [weights.x, weights.y, weights.z]
+                    .iter()
+                    .all(|&weight| weight.is_finite() && weight >= 0.0);
+                if !inside || !sum.is_finite() || sum <= 0.0 {
+                    return Err(CommandError::PokeOutsideFace(*weights));
+                }
+                poke(mesh, meta_index, *weights / sum);
+            }
+            Command::Pull => {
+                // Create new vertex
+                let new_index = mesh.add_vertex(mesh.positions[mesh.indices[meta_index] as usize]);
+
+                // Set the old face to use the new index
+                let old_index = mesh.indices[meta_index];
+                mesh.indices[meta_index] = new_index as i32;
+
+                // Add two new faces
+                let (start, offset) = decompose_meta_index(meta_index);
+                let num_indices = mesh.indices.len();
+                mesh.add_faces(
+                    [
+                        old_index,
+                        new_index as i32,
+                        mesh.indices[start + (offset + 2) % 3],
+                        new_index as i32,
+                        old_index,
+                        mesh.indices[start + (offset + 1) % 3],
+                    ],
+                    [
+                        (num_indices + 3) as i32,
+                        (start + (offset + 2) % 3) as i32,
+                        mesh.connections[start + (offset + 2) % 3],
+                        num_indices as i32,
+                        mesh.connections[meta_index],
+                        meta_index as i32,
+                    ],
+                );
+            }
+            Command::Extrude(distance) => {
+                if !distance.is_finite() {
+                    return Err(CommandError::ExtrudeDistanceNotFinite(*distance));
+                }
+                let (start, _) = decompose_meta_index(meta_index);
+                let offset = mesh.face_normal(start) * *distance;
+                ring_face(mesh, start, |position| position + offset);
+            }
+            Command::Inset(amount) => {
+                if !(0.0..1.0).contains(amount) {
+                    return Err(CommandError::InsetAmountOutOfRange(*amount));
+                }
+                let (start, _) = decompose_meta_index(meta_index);
+                let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[mesh.indices[start + i] as usize]);
+                let centroid = (a + b + c) / 3.0;
+                ring_face(mesh, start, |position| position.lerp(centroid, *amount));
+            }
+            Command::EdgeCollapse(amount) => {
+                if !(0.0..=1.0).contains(amount) {
+                    return Err(CommandError::CollapseAmountOutOfRange(*amount));
+                }
+                collapse_edge(mesh, meta_index, *amount)?;
+            }
+            Command::FlipEdge => flip_edge(mesh, meta_index)?,
+            Command::DeleteFace => delete_face(mesh, meta_index)?,
+            Command::FillHole => fill_hole(mesh, meta_index)?,
+            Command::Subdivide {
+                levels,
+                scheme,
+                target,
+            } => {
+                if *levels > MAX_SUBDIVIDE_LEVELS {
+                    return Err(CommandError::SubdivideLevelsOutOfRange(*levels));
+                }
+                let mut selected = target_faces(mesh, meta_index, *target)?;
+                for _ in 0..*levels {
+                    selected = subdivide(mesh, *scheme, &selected);
+                }
+            }
+            Command::Smooth {
+                iterations,
+                factor,
+                mode,
+                target,
+            } => {
+                if *iterations > MAX_SMOOTH_ITERATIONS {
+                    return Err(CommandError::SmoothIterationsOutOfRange(*iterations));
+                }
+                if !(0.0..=1.0).contains(factor) {
+                    return Err(CommandError::SmoothFactorOutOfRange(*factor));
+                }
+                let steps = match mode {
+                    Smoothing::Laplacian => vec![*factor],
+                    // Taubin's pass band frequency of 0.1 sets how far back out the second step goes
+                    Smoothing::Taubin => vec![*factor, 1.0 / (0.1 - 1.0 / *factor)],
+                };
+                smooth(mesh, meta_index, *target, *iterations, &steps)?;
+            }
+            Command::Color(color) => {
+                mesh.colors[mesh.indices[meta_index] as usize] = *color;
+            }
+        };
+        Ok(())
+    }
+}
+
+// Puts a new vertex in the face at `meta_index` and replaces the face with three that each run
+// along one of its edges and in to the new vertex. The face's first edge keeps its meta index, the
+// other two move to the new faces, and selections follow their edge.
+fn poke(mesh: &mut Mesh, meta_index: usize, weights: Vec3) {
+    let (start, offset) = decompose_meta_index(meta_index);
+    let corners = [0, 1, 2].map(|i| start + (offset + i) % 3);
+    let mut center = Vec3::ZERO;
+    let mut color = Color::from_rgba(0.0, 0.0, 0.0, 0.0);
+    for (corner, weight) in corners.into_iter().zip([weights.x, weights.y, weights.z]) {
+        let vertex = mesh.indices[corner] as usize;
+        center += mesh.positions[vertex] * weight;
+        color.r += mesh.colors[vertex].r * weight;
+        color.g += mesh.colors[vertex].g * weight;
+        color.b += mesh.colors[vertex].b * weight;
+        color.a += mesh.colors[vertex].a * weight;
+    }
+    let center_index = mesh.add_vertex(center) as i32;
+    mesh.colors[center_index as usize] = color;
+
+    // Face i runs along edge i of the old face and then to the center and back
+    let first = mesh.indices.len();
+    let [a, b, c] = [0, 1, 2].map(|i| mesh.indices[start + i]);
+    mesh.add_faces(
+        [b, c, center_index, c, a, center_index],
+        [
+            mesh.connections[start + 1],
+            (first + 5) as i32,
+            (start + 1) as i32,
+            mesh.connections[start + 2],
+            (start + 2) as i32,
+            (first + 1) as i32,
+        ],
+    );
+    mesh.indices[start + 2] = center_index;
+    for meta_index in mesh.tracked_indices.values_mut() {
+        match (*meta_index as usize).checked_sub(start) {
+            Some(1) => *meta_index = first as i32,
+            Some(2) => *meta_index = (first + 3) as i32,
+            _ => {}
+        }
+    }
+}
+
+// Every level quadruples the face count, so this is already 4096 faces for each one there was
+const MAX_SUBDIVIDE_LEVELS: u32 = 6;
+
+// Each iteration walks every vertex of the target, and past this many the shape has long settled
+const MAX_SMOOTH_ITERATIONS: u32 = 1000;
+
+// Replaces the face starting at `start` with a copy of itself, moved by `place`, and joins the two
+// with a ring of faces. The copy keeps the face's meta indices, so selections move onto it.
+fn ring_face(mesh: &mut Mesh, start: usize, place: impl Fn(Vec3) -> Vec3) {
+    let old_indices = [0, 1, 2].map(|i| mesh.indices[start + i]);
+    let outer_connections = [0, 1, 2].map(|i| mesh.connections[start + i]);
+
+    let new_indices = old_indices.map(|old_index| {
+        let old_index = old_index as usize;
+        let new_index = mesh.add_vertex(place(mesh.positions[old_index]));
+        mesh.colors[new_index] = mesh.colors[old_index];
+        new_index as i32
+    });
+    mesh.indices[start..start + 3].copy_from_slice(&new_indices);
+
+    // Two faces between each old edge and its copy. The first runs along the old edge and across
+    // to the copy, the second along the copy's edge and back.
+    let first_side = mesh.indices.len();
+    let side = |i: usize| (first_side + 6 * (i % 3)) as i32;
+    for i in 0..3 {
+        let next = (i + 1) % 3;
+        mesh.add_faces(
+            [
+                old_indices[i],
+                old_indices[next],
+                new_indices[next],
+                old_indices[i],
+                new_indices[next],
+                new_indices[i],
+            ],
+            [
+                outer_connections[i],
+                side(i + 1) + 5,
+                side(i) + 3,
+                side(i) + 2,
+                (start + i) as i32,
+                side(i + 2) + 1,
+            ],
+        );
+    }
+}
+
+// Makes two half-edges each other's twins. Either can be `Mesh::BOUNDARY`, leaving the other one on
+// the boundary.
+fn link(mesh: &mut Mesh, a: i32, b: i32) {
+    if a != Mesh::BOUNDARY {
+        mesh.connections[a as usize] = b;
+    }
+    if b != Mesh::BOUNDARY {
+        mesh.connections[b as usize] = a;
+    }
+}
+
+// The vertices joined to the vertex of a fan by an edge
+fn ring(mesh: &Mesh, fan: &[usize]) -> HashSet<i32> {
+    fan.iter()
+        .flat_map(|&corner| [next_corner(corner), previous_corner(corner)])
+        .map(|corner| mesh.indices[corner])
+        .collect()
+}
+
+fn on_boundary(mesh: &Mesh, fan: &[usize]) -> bool {
+    fan.iter().any(|&corner| mesh.twin(corner).is_none())
+}
+
+// Merges the two ends of the half-edge at `meta_index` into one vertex `amount` of the way along
+// it, removing the two faces on either side of the edge.
+//
+// The face with the half-edge has corners u, v and w, and the face across has v, u and x. The
+// edges around w and x that lose their face are linked to each other, and the vertex v is replaced
+// by u everywhere else. Vertices tied to u or v keep their own slots but move to the merged position.
+fn collapse_edge(mesh: &mut Mesh, meta_index: usize, amount: f32) -> Result<(), CommandError> {
+    let h = meta_index;
+    let t = mesh
+        .twin(h)
+        .ok_or(CommandError::BoundaryEdge(meta_index as i32))?;
+    let (h1, t1) = (next_corner(h), next_corner(t));
+    let (h2, t2) = (next_corner(h1), next_corner(t1));
+    let (u, v) = (mesh.indices[h] as usize, mesh.indices[h1] as usize);
+    let (w, x) = (mesh.indices[h2], mesh.indices[t2]);
+
+    // The ends can only share the two vertices opposite the edge, otherwise merging them would
+    // pinch the surface. Collapsing a tetrahedron would flatten it. Joining two boundaries through
+    // the middle of the surface, or losing both faces around w or x, would pinch it too.
+    let u_fan = mesh
+        .vertex_fan(h)
+        .ok_or(CommandError::BrokenVertexFan(h as i32))?;
+    let v_fan = mesh
+        .vertex_fan(h1)
+        .ok_or(CommandError::BrokenVertexFan(h1 as i32))?;
+    let shared: HashSet<i32> = ring(mesh, &u_fan)
+        .intersection(&ring(mesh, &v_fan))
+        .copied()
+        .collect();
+    let (a, b) = (mesh.connections[h1], mesh.connections[h2]);
+    let (c, d) = (mesh.connections[t1], mesh.connections[t2]);
+    if shared != HashSet::from([w, x])
+        || mesh.face_count() <= 4
+        || (on_boundary(mesh, &u_fan) && on_boundary(mesh, &v_fan))
+        || (a == Mesh::BOUNDARY && b == Mesh::BOUNDARY)
+        || (c == Mesh::BOUNDARY && d == Mesh::BOUNDARY)
+    {
+        return Err(CommandError::CollapseBreaksManifold(meta_index as i32));
+    }
+
+    // Selections on the removed faces move to the merged vertex in the face across from them
+    let merged_corner = |outgoing: i32, incoming: i32| match outgoing {
+        Mesh::BOUNDARY => next_corner(incoming as usize) as i32,
+        outgoing => outgoing,
+    };
+    let (face_selection, across_selection) = (merged_corner(b, a), merged_corner(d, c));
+    let (face, across_face) = (decompose_meta_index(h).0, decompose_meta_index(t).0);
+    for meta_index in mesh.tracked_indices.values_mut() {
+        match decompose_meta_index(*meta_index as usize).0 {
+            start if start == face => *meta_index = face_selection,
+            start if start == across_face => *meta_index = across_selection,
+            _ => {}
+        }
+    }
+
+    link(mesh, a, b);
+    link(mesh, c, d);
+    // Vertices tied to either end move along with it and stay tied
+    let position = mesh.positions[u].lerp(mesh.positions[v], amount);
+    for &corner in u_fan.iter().chain(&v_fan) {
+        mesh.positions[mesh.indices[corner] as usize] = position;
+    }
+    for corner in v_fan {
+        if mesh.indices[corner] == v as i32 {
+            mesh.indices[corner] = u as i32;
+        }
+    }
+    mesh.colors[u] = mesh.colors[u].lerp(mesh.colors[v], amount);
+    mesh.delete_vertex(v);
+
+    mesh.remove_face(face.max(across_face));
+    mesh.remove_face(face.min(across_face));
+    Ok(())
+}
+
+// Turns the edge between the faces (u, v, w) and (v, u, x) so it runs between w and x instead.
+//
+// Both faces keep their meta indices. The selected half-edge becomes the new edge running from w
+// to x, and any other selection on the two faces follows its half-edge to wherever it ends up.
+fn flip_edge(mesh: &mut Mesh, meta_index: usize) -> Result<(), CommandError> {
+    let h = meta_index;
+    let t = mesh
+        .twin(h)
+        .ok_or(CommandError::BoundaryEdge(meta_index as i32))?;
+    let (h1, t1) = (next_corner(h), next_corner(t));
+    let (h2, t2) = (next_corner(h1), next_corner(t1));
+    let [u, v, w, x] = [h, h1, h2, t2].map(|corner| mesh.indices[corner]);
+
+    let w_fan = mesh
+        .vertex_fan(h2)
+        .ok_or(CommandError::BrokenVertexFan(h2 as i32))?;
+    if w == x || ring(mesh, &w_fan).contains(&x) {
+        return Err(CommandError::FlipDuplicatesEdge(meta_index as i32));
+    }
+
+    // The new faces have to face the same way as the old ones, which also rules out flat ones
+    let position = |vertex: i32| mesh.positions[vertex as usize];
+    let facing = mesh.face_normal(h) + mesh.face_normal(t);
+    let winding = |a: i32, b: i32, c: i32| {
+        (position(c) - position(a))
+            .cross(position(b) - position(a))
+            .dot(facing)
+    };
+    if winding(w, x, v) <= 0.0 || winding(x, w, u) <= 0.0 {
+        return Err(CommandError::FlipMakesDegenerateFace(meta_index as i32));
+    }
+
+    let [a, b, c, d] = [h1, h2, t1, t2].map(|corner| mesh.connections[corner]);
+    for (corner, vertex, twin) in [
+        (h, w, t as i32),
+        (h1, x, d),
+        (h2, v, a),
+        (t, x, h as i32),
+        (t1, w, b),
+        (t2, u, c),
+    ] {
+        mesh.indices[corner] = vertex;
+        link(mesh, corner as i32, twin);
+    }
+
+    let moved = HashMap::from([(h1, h2), (h2, t1), (t1, t2), (t2, h1)]);
+    for meta_index in mesh.tracked_indices.values_mut() {
+        if let Some(&corner) = moved.get(&(*meta_index as usize)) {
+            *meta_index = corner as i32;
+        }
+    }
+    Ok(())
+}
+
+// Removes the face with the corner at `meta_index`, leaving a hole. Vertices no other face uses go
+// with it, and selections on it move across its edges to the neighbouring faces.
+fn delete_face(mesh: &mut Mesh, meta_index: usize) -> Result<(), CommandError> {
+    if mesh.face_count() == 1 {
+        return Err(CommandError::DeleteLastFace);
+    }
+    let (start, _) = decompose_meta_index(meta_index);
+    let corners = [start, start + 1, start + 2];
+
+    // A vertex already on a boundary would end up on two unless the hole grows into the old one
+    let mut unused = Vec::new();
+    for corner in corners {
+        let fan = mesh
+            .vertex_fan(corner)
+            .ok_or(CommandError::BrokenVertexFan(corner as i32))?;
+        if fan.len() == 1 {
+            unused.push(mesh.indices[corner] as usize);
+        } else if on_boundary(mesh, &fan)
+            && mesh.twin(corner).is_some()
+            && mesh.twin(previous_corner(corner)).is_some()
+        {
+            return Err(CommandError::DeleteBreaksManifold(meta_index as i32));
+        }
+    }
+
+    let twins = corners.map(|corner| mesh.twin(corner));
+    let fallback = twins.iter().flatten().next().copied().unwrap_or(0);
+    for tracked in mesh.tracked_indices.values_mut() {
+        let corner = *tracked as usize;
+        if decompose_meta_index(corner).0 == start {
+            *tracked = twins[corner - start].unwrap_or(fallback) as i32;
+        }
+    }
+
+    for twin in twins.into_iter().flatten() {
+        mesh.connections[twin] = Mesh::BOUNDARY;
+    }
+    for vertex in unused {
+        mesh.delete_vertex(vertex);
+    }
+    mesh.remove_face(start);
+    Ok(())
+}
+
+// Closes the hole the boundary half-edge at `meta_index` is on. A hole with two edges is closed by
+// joining them, three edges get one face, and bigger holes get a fan of faces around a new vertex
+// in the middle. The selection stays on the same half-edge, which now has a twin in the new faces.
+fn fill_hole(mesh: &mut Mesh, meta_index: usize) -> Result<(), CommandError> {
+    if mesh.twin(meta_index).is_some() {
+        return Err(CommandError::NotOnBoundary(meta_index as i32));
+    }
+    let boundary = mesh
+        .boundary_loop(meta_index)
+        .ok_or(CommandError::BrokenBoundary(meta_index as i32))?;
+    let corners: Vec<i32> = boundary
+        .iter()
+        .map(|&meta_index| mesh.indices[meta_index])
+        .collect();
+    let edges: Vec<i32> = boundary
+        .iter()
+        .map(|&meta_index| meta_index as i32)
+        .collect();
+
+    match boundary.len() {
+        2 => link(mesh, edges[0], edges[1]),
+        3 => mesh.add_face(
+            [corners[0], corners[2], corners[1]],
+            [edges[2], edges[1], edges[0]],
+        ),
+        sides => {
+            let count = sides as f32;
+            let mut center = Vec3::ZERO;
+            let mut color = Color::from_rgba(0.0, 0.0, 0.0, 0.0);
+            for &corner in corners.iter() {
+                let vertex = corner as usize;
+                center += mesh.positions[vertex] / count;
+                color.r += mesh.colors[vertex].r / count;
+                color.g += mesh.colors[vertex].g / count;
+                color.b += mesh.colors[vertex].b / count;
+                color.a += mesh.colors[vertex].a / count;
+            }
+            let center_index = mesh.add_vertex(center) as i32;
+            mesh.colors[center_index as usize] = color;
+
+            // Face i runs from the center to the end of edge i, back along it and to the center
+            let first_face = mesh.indices.len() as i32;
+            let face = |i: usize| first_face + 3 * (i % sides) as i32;
+            for i in 0..sides {
+                mesh.add_face(
+                    [center_index, corners[(i + 1) % sides], corners[i]],
+                    [face(i + 1) + 2, edges[i], face(i + sides - 1)],
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+// Splits every selected face into four: one at each corner and one in the middle, joining new
+// vertices in the middle of the edges. `selected` says which faces by face number. A face that
+// isn't selected but shares edges with one is cut along the new vertices on those edges into two or
+// three faces so no gaps open up, or into four like a selected one if all three of its edges were
+// split. Each face's children follow on from those of the faces before it, and a face split into
+// four has the three at its corners in order and then the one in the middle, so when every face is
+// selected face f becomes faces 4f to 4f + 3. Returns which of the new faces came from selected
+// ones.
+//
+// Loop subdivision places each new vertex 3/8 of the way toward both ends of its edge and 1/8
+// toward the two vertices opposite it, and pulls each old vertex toward its neighbours by a weight
+// that depends on how many there are. Along a boundary only the boundary edges count, so holes keep
+// their outline. Vertices and edges on the border of the selection aren't smoothed, so the faces
+// around it keep their shape.
+fn subdivide(mesh: &mut Mesh, scheme: Subdivision, selected: &[bool]) -> Vec<bool> {
+    let corners = mesh.indices.len();
+    let vertex_count = mesh.vertex_count();
+
+    // An edge is split when a face on either side of it is selected
+    let split: Vec<bool> = (0..corners)
+        .map(|corner| {
+            selected[corner / 3] || mesh.twin(corner).is_some_and(|twin| selected[twin / 3])
+        })
+        .collect();
+    let mut fixed = vec![false; vertex_count];
+    for corner in (0..corners).filter(|&corner| !selected[corner / 3]) {
+        fixed[mesh.indices[corner] as usize] = true;
+    }
+
+    let mut smoothed = Vec::new();
+    if scheme == Subdivision::Loop {
+        let mut neighbours = vec![(Vec3::ZERO, 0); vertex_count];
+        let mut boundary_neighbours = vec![(Vec3::ZERO, 0); vertex_count];
+        for corner in 0..corners {
+            let (a, b) = (
+                mesh.indices[corner] as usize,
+                mesh.indices[next_corner(corner)] as usize,
+            );
+            neighbours[a].0 += mesh.positions[b];
+            neighbours[a].1 += 1;
+            if mesh.twin(corner).is_none() {
+                boundary_neighbours[a].0 += mesh.positions[b];
+                boundary_neighbours[a].1 += 1;
+                boundary_neighbours[b].0 += mesh.positions[a];
+                boundary_neighbours[b].1 += 1;
+            }
+        }
+        smoothed = (0..vertex_count)
+            .map(|vertex| {
+                let old = mesh.positions[vertex];
+                match (boundary_neighbours[vertex], neighbours[vertex]) {
+                    _ if fixed[vertex] => old,
+                    ((sum, 2), _) => old * 0.75 + sum * 0.125,
+                    ((_, 0), (sum, count)) if count > 0 => {
+                        let count = count as f32;
+                        let beta = match count {
+                            3.0 => 3.0 / 16.0,
+                            _ => 3.0 / (8.0 * count),
+                        };
+                        old * (1.0 - count * beta) + sum * beta
+                    }
+                    _ => old,
+                }
+            })
+            .collect();
+    }
+
+    // Both half-edges of an edge share its new vertex
+    let mut midpoints = vec![-1; corners];
+    for corner in (0..corners).filter(|&corner| split[corner]) {
+        let twin = mesh.twin(corner);
+        if let Some(twin) = twin.filter(|&twin| twin < corner) {
+            midpoints[corner] = midpoints[twin];
+            continue;
+        }
+        let (a, b) = (
+            mesh.indices[corner] as usize,
+            mesh.indices[next_corner(corner)] as usize,
+        );
+        let opposite =
+            |corner: usize| mesh.positions[mesh.indices[previous_corner(corner)] as usize];
+        let midpoint = match (scheme, twin) {
+            (Subdivision::Loop, Some(twin)) if selected[corner / 3] && selected[twin / 3] => {
+                (mesh.positions[a] + mesh.positions[b]) * 0.375
+                    + (opposite(corner) + opposite(twin)) * 0.125
+            }
+            _ => mesh.positions[a].lerp(mesh.positions[b], 0.5),
+        };
+        let new_index = mesh.add_vertex(midpoint);
+        mesh.colors[new_index] = mesh.colors[a].lerp(mesh.colors[b], 0.5);
+        midpoints[corner] = new_index as i32;
+    }
+    mesh.positions[..smoothed.len()].copy_from_slice(&smoothed);
+
+    // The child half-edges along each old half-edge: the whole of it, or its first half and then
+    // its second half if it was split
+    let mut first_half = vec![0; corners];
+    let mut second_half = vec![0; corners];
+    let mut indices = Vec::with_capacity(corners * 4);
+    let mut connections = Vec::with_capacity(corners * 4);
+    let mut children_selected = Vec::with_capacity(corners * 4 / 3);
+    for start in (0..corners).step_by(3) {
+        let v: [i32; 3] = [0, 1, 2].map(|k| mesh.indices[start + k]);
+        let m: [i32; 3] = [0, 1, 2].map(|k| midpoints[start + k]);
+        let is_split = [0, 1, 2].map(|k| split[start + k]);
+        let children = match is_split {
+            [true, true, true] => vec![
+                [v[0], m[0], m[2]],
+                [v[1], m[1], m[0]],
+                [v[2], m[2], m[1]],
+                [m[0], m[1], m[2]],
+            ],
+            [false, false, false] => vec![v],
+            _ if is_split.iter().filter(|&&split| split).count() == 1 => {
+                // Cut from the new vertex to the corner across from it
+                let k = is_split.iter().position(|&split| split).unwrap();
+                let [a, b, c] = [v[k], v[(k + 1) % 3], v[(k + 2) % 3]];
+                vec![[a, m[k], c], [m[k], b, c]]
+            }
+            _ => {
+                // Cut off the corner between the two new vertices, then the rest in two
+                let k = is_split.iter().position(|&split| !split).unwrap();
+                let [a, b, c] = [v[k], v[(k + 1) % 3], v[(k + 2) % 3]];
+                let [bc, ca] = [m[(k + 1) % 3], m[(k + 2) % 3]];
+                vec![[a, b, bc], [a, bc, ca], [bc, c, ca]]
+            }
+        };
+
+        let first = indices.len();
+        for child in &children {
+            indices.extend(child);
+            children_selected.push(selected[start / 3]);
+        }
+        connections.resize(indices.len(), Mesh::BOUNDARY);
+        // Half-edges inside the face pair up with each other, the rest lie along its old edges
+        for corner in first..indices.len() {
+            let (from, to) = (indices[corner], indices[next_corner(corner)]);
+            let inner_twin = (first..indices.len())
+                .find(|&other| indices[other] == to && indices[next_corner(other)] == from);
+            if let Some(twin) = inner_twin {
+                connections[corner] = twin as i32;
+                continue;
+            }
+            for k in 0..3 {
+                let end = v[(k + 1) % 3];
+                if from == v[k] && to == if is_split[k] { m[k] } else { end } {
+                    first_half[start + k] = corner;
+                } else if is_split[k] && from == m[k] && to == end {
+                    second_half[start + k] = corner;
+                }
+            }
+        }
+    }
+    for corner in 0..corners {
+        let Some(twin) = mesh.twin(corner) else {
+            continue;
+        };
+        if split[corner] {
+            connections[first_half[corner]] = second_half[twin] as i32;
+            connections[second_half[corner]] = first_half[twin] as i32;
+        } else {
+            connections[first_half[corner]] = first_half[twin] as i32;
+        }
+    }
+    mesh.indices = indices;
+    mesh.connections = connections;
+    for meta_index in mesh.tracked_indices.values_mut() {
+        *meta_index = first_half[*meta_index as usize] as i32;
+    }
+    children_selected
+}
+
+fn pivot_position(mesh: &Mesh, meta_index: usize, pivot: Pivot) -> Vec3 {
+    match pivot {
+        Pivot::FaceCentroid => {
+            let (start, _) = decompose_meta_index(meta_index);
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[mesh.indices[start + i] as usize]);
+            (a + b + c) / 3.0
+        }
+        Pivot::Vertex => mesh.positions[mesh.indices[meta_index] as usize],
+        Pivot::Origin => Vec3::ZERO,
+        Pivot::BoundsCenter => {
+            let mut positions = mesh
+                .indices
+                .iter()
+                .map(|&index| mesh.positions[index as usize]);
+            let first = positions.next().unwrap_or(Vec3::ZERO);
+            let (min, max) = positions.fold((first, first), |(min, max), position| {
+                (
+                    Vec3::new(
+                        min.x.min(position.x),
+                        min.y.min(position.y),
+                        min.z.min(position.z),
+                    ),
+                    Vec3::new(
+                        max.x.max(position.x),
+                        max.y.max(position.y),
+                        max.z.max(position.z),
+                    ),
+                )
+            });
+            (min + max) / 2.0
+        }
+    }
+}
+
+// Moves every vertex of the target to wherever `place` puts it, along with the vertices tied to
+// them so the surface doesn't tear
+fn transform(
+    mesh: &mut Mesh,
+    meta_index: usize,
+    target: Target,
+    place: impl Fn(Vec3) -> Vec3,
+) -> Result<(), CommandError> {
+    let targets = target_vertices(mesh, meta_index, target);
+    let mut vertices = BTreeSet::new();
+    let mut seen = vec![false; mesh.indices.len()];
+    for corner in 0..mesh.indices.len() {
+        if seen[corner] || !targets.contains(&mesh.indices[corner]) {
+            continue;
+        }
+        let fan = mesh
+            .vertex_fan(corner)
+            .ok_or(CommandError::BrokenVertexFan(corner as i32))?;
+        for corner in fan {
+            seen[corner] = true;
+            vertices.insert(mesh.indices[corner] as usize);
+        }
+    }
+    for vertex in vertices {
+        mesh.positions[vertex] = place(mesh.positions[vertex]);
+    }
+    Ok(())
+}
+
+fn target_vertices(mesh: &Mesh, meta_index: usize, target: Target) -> BTreeSet<i32> {
+    let (start, _) = decompose_meta_index(meta_index);
+    match target {
+        Target::Vertex => BTreeSet::from([mesh.indices[meta_index]]),
+        Target::Face => mesh.indices[start..start + 3].iter().copied().collect(),
+        Target::Region => region(mesh, start)
+            .into_iter()
+            .flat_map(|face| mesh.indices[face..face + 3].iter().copied())
+            .collect(),
+    }
+}
+
+// The selected faces of the target by face number
+fn target_faces(mesh: &Mesh, meta_index: usize, target: Target) -> Result<Vec<bool>, CommandError> {
+    let mut selected = vec![false; mesh.face_count()];
+    let faces = match target {
+        Target::Vertex => mesh
+            .vertex_fan(meta_index)
+            .ok_or(CommandError::BrokenVertexFan(meta_index as i32))?,
+        Target::Face => vec![meta_index],
+        Target::Region => region(mesh, decompose_meta_index(meta_index).0),
+    };
+    for face in faces {
+        selected[face / 3] = true;
+    }
+    Ok(selected)
+}
+
+// The start of every face reachable from the one starting at `start` by crossing edges
+fn region(mesh: &Mesh, start: usize) -> Vec<usize> {
+    let mut visited = vec![false; mesh.face_count()];
+    visited[start / 3] = true;
+    let mut faces = vec![start];
+    let mut next = 0;
+    while let Some(&face) = faces.get(next) {
+        next += 1;
+        for corner in face..face + 3 {
+            if let Some(twin) = mesh.twin(corner) {
+                let (neighbour, _) = decompose_meta_index(twin);
+                if !visited[neighbour / 3] {
+                    visited[neighbour / 3] = true;
+                    faces.push(neighbour);
+                }
+            }
+        }
+    }
+    faces
+}
+
+// Relaxes the target's vertices once for every step in every iteration, moving each the step's
+// weight of the way toward the average of its neighbours. Negative weights move away from it.
+//
+// Tied vertices share a position, so they're treated as one: the lowest vertex of each fan stands
+// in for the rest while smoothing and they all get its position at the end.
+fn smooth(
+    mesh: &mut Mesh,
+    meta_index: usize,
+    target: Target,
+    iterations: u32,
+    steps: &[f32],
+) -> Result<(), CommandError> {
+    let corners = mesh.indices.len();
+    let mut logical: Vec<usize> = (0..mesh.vertex_count()).collect();
+    let mut seen = vec![false; corners];
+    for corner in 0..corners {
+        if seen[corner] {
+            continue;
+        }
+        let fan = mesh
+            .vertex_fan(corner)
+            .ok_or(CommandError::BrokenVertexFan(corner as i32))?;
+        let representative = fan.iter().map(|&corner| mesh.indices[corner]).min();
+        let representative = representative.unwrap_or(mesh.indices[corner]) as usize;
+        for corner in fan {
+            seen[corner] = true;
+            logical[mesh.indices[corner] as usize] = representative;
+        }
+    }
+
+    let mut neighbours = vec![HashSet::new(); logical.len()];
+    let mut boundary_neighbours = vec![HashSet::new(); logical.len()];
+    for corner in 0..corners {
+        let a = logical[mesh.indices[corner] as usize];
+        let b = logical[mesh.indices[next_corner(corner)] as usize];
+        neighbours[a].insert(b);
+        neighbours[b].insert(a);
+        if mesh.twin(corner).is_none() {
+            boundary_neighbours[a].insert(b);
+            boundary_neighbours[b].insert(a);
+        }
+    }
+
+    let moving: BTreeSet<usize> = target_vertices(mesh, meta_index, target)
+        .into_iter()
+        .map(|vertex| logical[vertex as usize])
+        .collect();
+    for _ in 0..iterations {
+        for &weight in steps {
+            let moved: Vec<(usize, Vec3)> = moving
+                .iter()
+                .map(|&vertex| {
+                    let ring = match boundary_neighbours[vertex].is_empty() {
+                        true => &neighbours[vertex],
+                        false => &boundary_neighbours[vertex],
+                    };
+                    let position = mesh.positions[vertex];
+                    if ring.is_empty() {
+                        return (vertex, position);
+                    }
+                    let mut average = Vec3::ZERO;
+                    for &neighbour in ring {
+                        average += mesh.positions[neighbour];
+                    }
+                    average = average / ring.len() as f32;
+                    (vertex, position + (average - position) * weight)
+                })
+                .collect();
+            for (vertex, position) in moved {
+                mesh.positions[vertex] = position;
+            }
+        }
+    }
+
+    for (vertex, &representative) in logical.iter().enumerate() {
+        if moving.contains(&representative) {
+            mesh.positions[vertex] = mesh.positions[representative];
+        }
+    }
+    Ok(())
+}
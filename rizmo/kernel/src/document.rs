@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+use crate::command::{Command, Target};
+use crate::error::DocumentError;
+use crate::math::{Color, Vec3};
+use crate::mesh::Mesh;
+use crate::primitive::Primitive;
+
+/// The version of the saved format this build writes. Bump it whenever the format changes and add
+/// a migration from the previous version to MIGRATIONS.
+pub const FORMAT_VERSION: u32 = 6;
+
+/// The version of gizmo writing documents.
+pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Upgrades a document from the version at its index to the next one, so MIGRATIONS[0] takes a
+// version 0 document to version 1 and so on.
+//
+// Version history:
+// 0. An object of commands keyed by their id.
+// 1. An array of commands in order.
+// 2. An object with the command array and an optional base mesh.
+// 3. Adds format_version, app_version and metadata.
+// 4. Translate holds its delta and the space it's measured in instead of just the delta.
+// 5. Adds the primitive the commands start from when there's no base mesh.
+// 6. Subdivide holds a target instead of always splitting every face.
+const MIGRATIONS: [fn(Value) -> Result<Value, DocumentError>; FORMAT_VERSION as usize] = [
+    from_id_map,
+    wrap_commands,
+    add_version,
+    add_translate_space,
+    add_primitive,
+    add_subdivide_target,
+];
+
+/// Everything a saved model is made of.
+#[derive(Clone, Debug)]
+pub struct Document {
+    /// The version of gizmo that wrote the document, if it said.
+    pub app_version: Option<String>,
+    /// The mesh the commands start from, None for the primitive.
+    pub base: Option<Mesh>,
+    /// What the commands start from when there's no base mesh.
+    pub primitive: Primitive,
+    pub commands: Vec<Command>,
+    /// Free-form notes about the model, such as its author or description.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Document {
+            app_version: Some(APP_VERSION.to_string()),
+            base: None,
+            primitive: Primitive::default(),
+            commands: Vec::new(),
+            metadata: BTreeMap::new(),
+        }
+    }
+}
+
+impl Document {
+    /// Saves the document in the current format.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&SavedDocument {
+            format_version: FORMAT_VERSION,
+            app_version: self.app_version.clone(),
+            base: self.base.as_ref().map(SavedMesh::from),
+            primitive: self.primitive,
+            commands: self.commands.clone(),
+            metadata: self.metadata.clone(),
+        })
+    }
+
+    /// Loads a document saved in the current format or any earlier one, upgrading it on the way.
+    pub fn from_json(string: &str) -> Result<Document, DocumentError> {
+        let mut value: Value = serde_json::from_str(string)?;
+        let version = format_version(&value)?;
+        if version > FORMAT_VERSION {
+            return Err(DocumentError::NewerVersion(version));
+        }
+        for migration in &MIGRATIONS[version as usize..] {
+            value = migration(value)?;
+        }
+
+        let saved: SavedDocument = serde_json::from_value(value)?;
+        Ok(Document {
+            app_version: saved.app_version,
+            base: saved.base.as_ref().map(SavedMesh::to_mesh).transpose()?,
+            primitive: saved.primitive,
+            commands: saved.commands,
+            metadata: saved.metadata,
+        })
+    }
+}
+
+// The current format
+#[derive(Serialize, Deserialize)]
+struct SavedDocument {
+    format_version: u32,
+    #[serde(default)]
+    app_version: Option<String>,
+    #[serde(default)]
+    base: Option<SavedMesh>,
+    #[serde(default)]
+    primitive: Primitive,
+    commands: Vec<Command>,
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+}
+
+// The live part of a mesh. Connections aren't saved, they're rebuilt when loading.
+#[derive(Serialize, Deserialize)]
+struct SavedMesh {
+    positions: Vec<Vec3>,
+    colors: Vec<Color>,
+    indices: Vec<i32>,
+}
+
+impl From<&Mesh> for SavedMesh {
+    fn from(mesh: &Mesh) -> Self {
+        SavedMesh {
+            positions: mesh.positions[..mesh.vertex_count()].to_vec(),
+            colors: mesh.colors[..mesh.vertex_count()].to_vec(),
+            indices: mesh.indices.clone(),
+        }
+    }
+}
+
+impl SavedMesh {
+    fn to_mesh(&self) -> Result<Mesh, DocumentError> {
+        if !self.indices.len().is_multiple_of(3) || self.indices.iter().any(|&index| index < 0) {
+            return Err(DocumentError::Invalid(
+                "base mesh indices aren't triangles".into(),
+            ));
+        }
+        let triangles: Vec<[usize; 3]> = self
+            .indices
+            .chunks_exact(3)
+            .map(|face| [0, 1, 2].map(|i| face[i] as usize))
+            .collect();
+        // Base meshes may have had faces deleted
+        Ok(Mesh::from_triangles_with_boundaries(
+            &self.positions,
+            &self.colors,
+            &triangles,
+        )?)
+    }
+}
+
+// Documents only started saying which version they are at version 3, earlier ones are told apart
+// by their shape
+fn format_version(value: &Value) -> Result<u32, DocumentError> {
+    match value {
+        Value::Array(_) => Ok(1),
+        Value::Object(map) => match map.get("format_version") {
+            Some(version) => version
+                .as_u64()
+                .map(|version| version.min(u32::MAX as u64) as u32)
+                .ok_or_else(|| DocumentError::Invalid("format_version isn't a number".into())),
+            None if map.contains_key("commands") => Ok(2),
+            None => Ok(0),
+        },
+        _ => Err(DocumentError::Invalid(
+            "expected an object or an array".into(),
+        )),
+    }
+}
+
+fn from_id_map(value: Value) -> Result<Value, DocumentError> {
+    let Value::Object(map) = value else {
+        unreachable!("version 0 documents are objects");
+    };
+    let mut commands = map
+        .into_iter()
+        .map(|(key, command)| {
+            let id: i64 = key
+                .parse()
+                .map_err(|_| DocumentError::Invalid(format!("invalid command id \"{key}\"")))?;
+            Ok((id, command))
+        })
+        .collect::<Result<Vec<(i64, Value)>, DocumentError>>()?;
+    commands.sort_by_key(|(id, _)| *id);
+    Ok(Value::Array(
+        commands.into_iter().map(|(_, command)| command).collect(),
+    ))
+}
+
+fn wrap_commands(value: Value) -> Result<Value, DocumentError> {
+    Ok(json!({ "commands": value }))
+}
+
+fn add_version(value: Value) -> Result<Value, DocumentError> {
+    let Value::Object(mut map) = value else {
+        unreachable!("version 2 documents are objects");
+    };
+    map.insert("format_version".into(), json!(3));
+    map.insert("metadata".into(), Value::Object(Map::new()));
+    Ok(Value::Object(map))
+}
+
+fn add_translate_space(value: Value) -> Result<Value, DocumentError> {
+    let Value::Object(mut map) = value else {
+        unreachable!("version 3 documents are objects");
+    };
+    if let Some(Value::Array(commands)) = map.get_mut("commands") {
+        for command in commands {
+            if let Some(delta) = command.get_mut("Translate") {
+                *delta = json!({ "delta": delta.take(), "space": "World" });
+            }
+        }
+    }
+    map.insert("format_version".into(), json!(4));
+    Ok(Value::Object(map))
+}
+
+fn add_primitive(value: Value) -> Result<Value, DocumentError> {
+    let Value::Object(mut map) = value else {
+        unreachable!("version 4 documents are objects");
+    };
+    map.insert("format_version".into(), json!(5));
+    map.insert("primitive".into(), json!(Primitive::Tetrahedron));
+    Ok(Value::Object(map))
+}
+
+fn add_subdivide_target(value: Value) -> Result<Value, DocumentError> {
+    let Value::Object(mut map) = value else {
+        unreachable!("version 5 documents are objects");
+    };
+    if let Some(Value::Array(commands)) = map.get_mut("commands") {
+        for command in commands {
+            if let Some(Value::Object(subdivide)) = command.get_mut("Subdivide") {
+                subdivide.insert("target".into(), json!(Target::Region));
+            }
+        }
+    }
+    map.insert("format_version".into(), json!(6));
+    Ok(Value::Object(map))
+}
@@ -0,0 +1,263 @@
+use std::fmt;
+
+use crate::math::Vec3;
+use crate::mesh::MetaIndexId;
+use crate::stack::CommandId;
+use crate::validate::ValidationReport;
+
+/// Why a command couldn't run. Commands check for these before touching the mesh, so a failed
+/// command leaves the mesh as it was.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandError {
+    /// The selection stack is empty.
+    NoSelection,
+    /// Popping would leave the selection stack empty.
+    PopLastSelection,
+    /// The meta index id isn't tracked by the mesh.
+    UntrackedSelection(MetaIndexId),
+    /// The meta index doesn't name a corner of the mesh.
+    MetaIndexOutOfRange(i32),
+    /// Split amounts have to be between 0 and 1.
+    SplitAmountOutOfRange(f32),
+    /// Poke weights have to be finite and not negative, and add up to more than 0.
+    PokeOutsideFace(Vec3),
+    /// Extrude distances have to be finite.
+    ExtrudeDistanceNotFinite(f32),
+    /// Inset amounts have to be at least 0 and less than 1.
+    InsetAmountOutOfRange(f32),
+    /// Collapse amounts have to be between 0 and 1.
+    CollapseAmountOutOfRange(f32),
+    /// Smooth factors have to be between 0 and 1.
+    SmoothFactorOutOfRange(f32),
+    /// Smoothing is limited to a thousand iterations, since each one goes over the whole target.
+    SmoothIterationsOutOfRange(u32),
+    /// Collapsing the edge at this meta index would pinch the surface into a non-manifold shape.
+    CollapseBreaksManifold(i32),
+    /// The vertices opposite the edge at this meta index are already joined by an edge.
+    FlipDuplicatesEdge(i32),
+    /// Flipping the edge at this meta index would leave a face flat or facing the wrong way.
+    FlipMakesDegenerateFace(i32),
+    /// The half-edge at this meta index is on a boundary, so there's no face across it.
+    BoundaryEdge(i32),
+    /// The half-edge at this meta index isn't on a boundary, so there's no hole to fill.
+    NotOnBoundary(i32),
+    /// Walking along the boundary from this meta index never got back to it.
+    BrokenBoundary(i32),
+    /// Deleting the only face would leave nothing to select.
+    DeleteLastFace,
+    /// Deleting the face at this meta index would leave a vertex between two holes.
+    DeleteBreaksManifold(i32),
+    /// Subdividing is limited to a handful of levels, since every level makes four times the faces.
+    SubdivideLevelsOutOfRange(u32),
+    /// Scale factors have to be finite.
+    ScaleFactorNotFinite(Vec3),
+    /// Rotation axes and angles have to be finite.
+    RotationNotFinite(Vec3, f32),
+    /// Rotations need an axis with some length.
+    ZeroRotationAxis,
+    /// Walking around the vertex at this meta index never got back to it.
+    BrokenVertexFan(i32),
+    /// The command left the mesh breaking the invariants checked by `Mesh::validate`.
+    InvalidTopology(ValidationReport),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::NoSelection => write!(f, "there is no selection"),
+            CommandError::PopLastSelection => write!(f, "can't pop the last selection"),
+            CommandError::UntrackedSelection(id) => write!(f, "selection {id} isn't tracked"),
+            CommandError::MetaIndexOutOfRange(meta_index) => {
+                write!(f, "meta index {meta_index} is out of range")
+            }
+            CommandError::SplitAmountOutOfRange(amount) => {
+                write!(f, "split amount {amount} has to be between 0 and 1")
+            }
+            CommandError::PokeOutsideFace(weights) => {
+                write!(
+                    f,
+                    "poke weights {} {} {} aren't inside the face",
+                    weights.x, weights.y, weights.z
+                )
+            }
+            CommandError::ExtrudeDistanceNotFinite(distance) => {
+                write!(f, "extrude distance {distance} isn't a finite number")
+            }
+            CommandError::InsetAmountOutOfRange(amount) => {
+                write!(
+                    f,
+                    "inset amount {amount} has to be at least 0 and less than 1"
+                )
+            }
+            CommandError::CollapseAmountOutOfRange(amount) => {
+                write!(f, "collapse amount {amount} has to be between 0 and 1")
+            }
+            CommandError::SmoothFactorOutOfRange(factor) => {
+                write!(f, "smooth factor {factor} has to be between 0 and 1")
+            }
+            CommandError::SmoothIterationsOutOfRange(iterations) => {
+                write!(f, "can't smooth {iterations} times, 1000 is the most")
+            }
+            CommandError::CollapseBreaksManifold(meta_index) => {
+                write!(
+                    f,
+                    "collapsing the edge at meta index {meta_index} would pinch the mesh"
+                )
+            }
+            CommandError::FlipDuplicatesEdge(meta_index) => {
+                write!(
+                    f,
+                    "flipping the edge at meta index {meta_index} would duplicate an edge"
+                )
+            }
+            CommandError::FlipMakesDegenerateFace(meta_index) => {
+                write!(
+                    f,
+                    "flipping the edge at meta index {meta_index} would fold or flatten a face"
+                )
+            }
+            CommandError::BoundaryEdge(meta_index) => {
+                write!(f, "meta index {meta_index} is on a boundary")
+            }
+            CommandError::NotOnBoundary(meta_index) => {
+                write!(f, "meta index {meta_index} isn't on a boundary")
+            }
+            CommandError::BrokenBoundary(meta_index) => {
+                write!(
+                    f,
+                    "the boundary from meta index {meta_index} doesn't loop back"
+                )
+            }
+            CommandError::DeleteLastFace => write!(f, "can't delete the last face"),
+            CommandError::DeleteBreaksManifold(meta_index) => {
+                write!(
+                    f,
+                    "deleting the face at meta index {meta_index} would pinch the mesh"
+                )
+            }
+            CommandError::SubdivideLevelsOutOfRange(levels) => {
+                write!(f, "can't subdivide {levels} levels, 6 is the most")
+            }
+            CommandError::ScaleFactorNotFinite(factor) => {
+                write!(
+                    f,
+                    "scale factor {} {} {} isn't finite",
+                    factor.x, factor.y, factor.z
+                )
+            }
+            CommandError::RotationNotFinite(axis, angle) => {
+                write!(
+                    f,
+                    "can't rotate {angle} degrees around {} {} {}, both have to be finite",
+                    axis.x, axis.y, axis.z
+                )
+            }
+            CommandError::ZeroRotationAxis => write!(f, "the rotation axis has no length"),
+            CommandError::BrokenVertexFan(meta_index) => {
+                write!(
+                    f,
+                    "the faces around meta index {meta_index} don't loop back"
+                )
+            }
+            CommandError::InvalidTopology(report) => write!(f, "invalid topology: {report}"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Why a mesh couldn't be built from a list of triangles.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeshError {
+    /// There are no triangles to build a mesh from.
+    NoFaces,
+    /// The face refers to a vertex that doesn't exist.
+    VertexOutOfRange { face: usize, vertex: usize },
+    /// The face uses the same vertex more than once.
+    DegenerateFace { face: usize },
+    /// No face runs along the edge in the other direction, so the surface isn't closed.
+    OpenEdge { from: usize, to: usize },
+    /// Several faces run along the edge in the same direction, either because more than two faces
+    /// meet there or because their windings disagree.
+    NonManifoldEdge { from: usize, to: usize },
+    /// The triangles pair up but still break the invariants checked by `Mesh::validate`.
+    InvalidTopology(ValidationReport),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::NoFaces => write!(f, "there are no faces"),
+            MeshError::VertexOutOfRange { face, vertex } => {
+                write!(f, "face {face} uses vertex {vertex} which doesn't exist")
+            }
+            MeshError::DegenerateFace { face } => {
+                write!(f, "face {face} uses the same vertex more than once")
+            }
+            MeshError::OpenEdge { from, to } => {
+                write!(f, "the edge from {from} to {to} is open")
+            }
+            MeshError::NonManifoldEdge { from, to } => {
+                write!(f, "more than one face runs from {from} to {to}")
+            }
+            MeshError::InvalidTopology(report) => write!(f, "invalid topology: {report}"),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// Why a saved document couldn't be loaded.
+#[derive(Debug)]
+pub enum DocumentError {
+    Json(serde_json::Error),
+    /// The document was saved in a newer format than this build understands.
+    NewerVersion(u32),
+    /// The JSON parses but isn't shaped like a document.
+    Invalid(String),
+    /// The base mesh can't be rebuilt.
+    Mesh(MeshError),
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocumentError::Json(error) => write!(f, "{error}"),
+            DocumentError::NewerVersion(version) => write!(
+                f,
+                "the document is format version {version}, which is newer than this version of gizmo"
+            ),
+            DocumentError::Invalid(message) => write!(f, "{message}"),
+            DocumentError::Mesh(error) => write!(f, "invalid base mesh: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+impl From<serde_json::Error> for DocumentError {
+    fn from(error: serde_json::Error) -> Self {
+        DocumentError::Json(error)
+    }
+}
+
+impl From<MeshError> for DocumentError {
+    fn from(error: MeshError) -> Self {
+        DocumentError::Mesh(error)
+    }
+}
+
+/// The command that stopped a replay and why.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayError {
+    pub command_id: CommandId,
+    pub error: CommandError,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "command {} failed: {}", self.command_id, self.error)
+    }
+}
+
+impl std::error::Error for ReplayError {}
@@ -0,0 +1,212 @@
+use std::io::{self, Write};
+
+use serde_json::{Value, json};
+
+use crate::io::Welded;
+use crate::math::Vec3;
+use crate::mesh::Mesh;
+
+// glTF constants
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const TRIANGLES: u32 = 4;
+
+// GLB constants
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+/// Writes the mesh as a glTF 2.0 file with its buffer embedded as a data URI.
+pub fn write_gltf(mesh: &Mesh, mut writer: impl Write) -> io::Result<()> {
+    let (mut document, buffer) = build(mesh);
+    document["buffers"][0]["uri"] = Value::from(format!(
+        "data:application/octet-stream;base64,{}",
+        base64(&buffer)
+    ));
+    serde_json::to_writer(&mut writer, &document)?;
+    writer.flush()
+}
+
+/// Writes the mesh as a binary glTF 2.0 file.
+pub fn write_glb(mesh: &Mesh, mut writer: impl Write) -> io::Result<()> {
+    let (document, mut buffer) = build(mesh);
+    let mut json = serde_json::to_vec(&document)?;
+
+    // Both chunks have to be 4 byte aligned, JSON is padded with spaces and binary with zeros
+    json.resize(json.len().next_multiple_of(4), b' ');
+    buffer.resize(buffer.len().next_multiple_of(4), 0);
+    let length = 12 + 8 + json.len() + 8 + buffer.len();
+
+    writer.write_all(&GLB_MAGIC.to_le_bytes())?;
+    writer.write_all(&GLB_VERSION.to_le_bytes())?;
+    writer.write_all(&(length as u32).to_le_bytes())?;
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(&CHUNK_JSON.to_le_bytes())?;
+    writer.write_all(&json)?;
+    writer.write_all(&(buffer.len() as u32).to_le_bytes())?;
+    writer.write_all(&CHUNK_BIN.to_le_bytes())?;
+    writer.write_all(&buffer)?;
+    writer.flush()
+}
+
+// Builds the glTF document and the single buffer it refers to. The buffer holds positions,
+// normals, colors and indices back to back, each in its own buffer view.
+fn build(mesh: &Mesh) -> (Value, Vec<u8>) {
+    let welded = Welded::new(mesh);
+    // glTF faces wind counter-clockwise
+    let triangles: Vec<[usize; 3]> = welded.triangles(mesh).map(|[a, b, c]| [a, c, b]).collect();
+
+    let positions: Vec<Vec3> = welded
+        .vertices
+        .iter()
+        .map(|&slot| mesh.positions[slot])
+        .collect();
+    let normals = vertex_normals(mesh, &welded);
+
+    let mut buffer = Vec::new();
+    let mut views = Vec::new();
+    let mut add_view = |bytes: Vec<u8>, target: u32| {
+        views.push(json!({
+            "buffer": 0,
+            "byteOffset": buffer.len(),
+            "byteLength": bytes.len(),
+            "target": target,
+        }));
+        buffer.extend(bytes);
+        views.len() - 1
+    };
+    let position_view = add_view(vec3_bytes(&positions), ARRAY_BUFFER);
+    let normal_view = add_view(vec3_bytes(&normals), ARRAY_BUFFER);
+    let color_view = add_view(
+        welded
+            .vertices
+            .iter()
+            .flat_map(|&slot| {
+                let color = mesh.colors[slot];
+                [color.r, color.g, color.b, color.a]
+            })
+            .flat_map(f32::to_le_bytes)
+            .collect(),
+        ARRAY_BUFFER,
+    );
+    let index_view = add_view(
+        triangles
+            .iter()
+            .flatten()
+            .flat_map(|&index| (index as u32).to_le_bytes())
+            .collect(),
+        ELEMENT_ARRAY_BUFFER,
+    );
+
+    let (min, max) = bounds(&positions);
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "gizmo" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0, "name": "gizmo" }],
+        "meshes": [{
+            "name": "gizmo",
+            "primitives": [{
+                "attributes": { "POSITION": 0, "NORMAL": 1, "COLOR_0": 2 },
+                "indices": 3,
+                "mode": TRIANGLES,
+            }],
+        }],
+        "accessors": [
+            {
+                "bufferView": position_view,
+                "componentType": FLOAT,
+                "count": positions.len(),
+                "type": "VEC3",
+                "min": [min.x, min.y, min.z],
+                "max": [max.x, max.y, max.z],
+            },
+            {
+                "bufferView": normal_view,
+                "componentType": FLOAT,
+                "count": normals.len(),
+                "type": "VEC3",
+            },
+            {
+                "bufferView": color_view,
+                "componentType": FLOAT,
+                "count": welded.vertices.len(),
+                "type": "VEC4",
+            },
+            {
+                "bufferView": index_view,
+                "componentType": UNSIGNED_INT,
+                "count": triangles.len() * 3,
+                "type": "SCALAR",
+            },
+        ],
+        "bufferViews": views,
+        "buffers": [{ "byteLength": buffer.len() }],
+    });
+    (document, buffer)
+}
+
+// Smooth normals, the average of the normals of every face around a vertex
+fn vertex_normals(mesh: &Mesh, welded: &Welded) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; welded.vertices.len()];
+    for (face, corners) in mesh.indices.chunks_exact(3).enumerate() {
+        let normal = mesh.face_normal(face * 3);
+        for &slot in corners {
+            normals[welded.remap[slot as usize].unwrap()] += normal;
+        }
+    }
+    normals
+        .into_iter()
+        .map(|normal| match normal.normalized() {
+            // Vertices only used by faces without area still need a unit normal
+            Vec3::ZERO => Vec3::new(0.0, 1.0, 0.0),
+            normal => normal,
+        })
+        .collect()
+}
+
+fn vec3_bytes(vectors: &[Vec3]) -> Vec<u8> {
+    vectors
+        .iter()
+        .flat_map(|v| [v.x, v.y, v.z])
+        .flat_map(f32::to_le_bytes)
+        .collect()
+}
+
+fn bounds(positions: &[Vec3]) -> (Vec3, Vec3) {
+    let mut min = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    let mut max = Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for position in positions {
+        min = Vec3::new(
+            min.x.min(position.x),
+            min.y.min(position.y),
+            min.z.min(position.z),
+        );
+        max = Vec3::new(
+            max.x.max(position.x),
+            max.y.max(position.y),
+            max.z.max(position.z),
+        );
+    }
+    (min, max)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = [0, 1, 2].map(|i| chunk.get(i).copied().unwrap_or(0) as u32);
+        let bits = (group[0] << 16) | (group[1] << 8) | group[2];
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
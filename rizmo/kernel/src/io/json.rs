@@ -0,0 +1,27 @@
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::math::{Color, Vec3};
+use crate::mesh::Mesh;
+
+// A dump of the live parts of a mesh, connections included, mostly useful for inspecting the
+// results of a replay or diffing them between versions.
+#[derive(Serialize)]
+struct JsonMesh<'a> {
+    positions: &'a [Vec3],
+    colors: &'a [Color],
+    indices: &'a [i32],
+    connections: &'a [i32],
+}
+
+pub fn write_json(mesh: &Mesh, mut writer: impl Write) -> io::Result<()> {
+    let json = JsonMesh {
+        positions: &mesh.positions[..mesh.vertex_count()],
+        colors: &mesh.colors[..mesh.vertex_count()],
+        indices: &mesh.indices,
+        connections: &mesh.connections,
+    };
+    serde_json::to_writer(&mut writer, &json)?;
+    writer.flush()
+}
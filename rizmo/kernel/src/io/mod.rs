@@ -0,0 +1,251 @@
+//! Reading and writing meshes in other formats.
+
+pub mod gltf;
+pub mod json;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::error::MeshError;
+use crate::math::{Color, Vec3};
+use crate::mesh::Mesh;
+
+// Vertex slots that modify_vertex moves together, merged into one vertex for formats that only
+// have a single index per corner.
+pub(crate) struct Welded {
+    /// The slot each output vertex takes its attributes from.
+    pub vertices: Vec<usize>,
+    /// The output vertex for each slot, None for slots no face uses.
+    pub remap: Vec<Option<usize>>,
+}
+
+impl Welded {
+    pub fn new(mesh: &Mesh) -> Self {
+        let mut parents: Vec<usize> = (0..mesh.vertex_count()).collect();
+        fn find(parents: &mut [usize], slot: usize) -> usize {
+            let mut root = slot;
+            while parents[root] != root {
+                root = parents[root];
+            }
+            parents[slot] = root;
+            root
+        }
+
+        let mut visited = vec![false; mesh.indices.len()];
+        for meta_index in 0..mesh.indices.len() {
+            if visited[meta_index] {
+                continue;
+            }
+            let fan = mesh.vertex_fan(meta_index).unwrap_or(vec![meta_index]);
+            for corner in fan {
+                visited[corner] = true;
+                let root = find(&mut parents, mesh.indices[meta_index] as usize);
+                let other = find(&mut parents, mesh.indices[corner] as usize);
+                parents[other.max(root)] = other.min(root);
+            }
+        }
+
+        let mut used = vec![false; mesh.vertex_count()];
+        for &index in mesh.indices.iter() {
+            used[index as usize] = true;
+        }
+        let mut vertices = Vec::new();
+        let mut remap = vec![None; mesh.vertex_count()];
+        for slot in 0..mesh.vertex_count() {
+            if !used[slot] {
+                continue;
+            }
+            let root = find(&mut parents, slot);
+            if remap[root].is_none() {
+                remap[root] = Some(vertices.len());
+                vertices.push(slot);
+            }
+            remap[slot] = remap[root];
+        }
+
+        Welded { vertices, remap }
+    }
+
+    /// The welded triangles, leaving out any that collapsed into a line or a point.
+    pub fn triangles<'a>(&'a self, mesh: &'a Mesh) -> impl Iterator<Item = [usize; 3]> + 'a {
+        mesh.indices.chunks_exact(3).filter_map(|face| {
+            let triangle = [0, 1, 2].map(|i| self.remap[face[i] as usize].unwrap());
+            let degenerate = triangle[0] == triangle[1]
+                || triangle[1] == triangle[2]
+                || triangle[2] == triangle[0];
+            (!degenerate).then_some(triangle)
+        })
+    }
+}
+
+/// What had to change to turn imported polygons into a mesh gizmo can edit.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct ImportReport {
+    /// Vertices merged into another one at the same position.
+    pub welded_vertices: usize,
+    /// Triangles dropped because welding collapsed them.
+    pub degenerate_faces: usize,
+    /// Triangles dropped because another one already covered the same corners.
+    pub duplicate_faces: usize,
+    /// Triangles turned over to agree with their neighbours about which way is out.
+    pub flipped_faces: usize,
+}
+
+impl ImportReport {
+    /// Whether the polygons went in untouched apart from welding.
+    pub fn is_clean(&self) -> bool {
+        self.degenerate_faces == 0 && self.duplicate_faces == 0 && self.flipped_faces == 0
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "welded {} vertices, dropped {} degenerate and {} duplicate faces, flipped {} faces",
+            self.welded_vertices, self.degenerate_faces, self.duplicate_faces, self.flipped_faces
+        )
+    }
+}
+
+/// Turns polygons wound counter-clockwise, the way most formats wind them, into a mesh. Vertices
+/// at the same position are welded, polygons are split into fans and connections are built.
+///
+/// With `repair`, faces that welding collapsed or that repeat another face are dropped and faces
+/// wound against their neighbours are turned over. Without it, those are errors. Open edges are
+/// left on a boundary, but non-manifold edges are always errors since there's no sensible way to
+/// pull them apart.
+pub fn build_mesh(
+    positions: &[Vec3],
+    colors: &[Color],
+    polygons: &[Vec<usize>],
+    repair: bool,
+) -> Result<(Mesh, ImportReport), MeshError> {
+    let mut report = ImportReport::default();
+
+    let mut welded_positions = Vec::new();
+    let mut welded_colors = Vec::new();
+    let mut welded_ids = HashMap::new();
+    let welded: Vec<usize> = positions
+        .iter()
+        .enumerate()
+        .map(|(vertex, position)| {
+            // Adding zero turns -0 into 0 so they weld together
+            let key = [position.x, position.y, position.z].map(|c| (c + 0.0).to_bits());
+            *welded_ids.entry(key).or_insert_with(|| {
+                welded_positions.push(*position);
+                welded_colors.push(colors.get(vertex).copied().unwrap_or_default());
+                welded_positions.len() - 1
+            })
+        })
+        .collect();
+    report.welded_vertices = positions.len() - welded_positions.len();
+
+    let mut triangles = Vec::new();
+    let mut seen = HashSet::new();
+    for (face, polygon) in polygons.iter().enumerate() {
+        if let Some(&vertex) = polygon.iter().find(|&&vertex| vertex >= positions.len()) {
+            return Err(MeshError::VertexOutOfRange { face, vertex });
+        }
+        for i in 1..polygon.len().saturating_sub(1) {
+            // Clockwise, the same as Godot
+            let triangle = [polygon[0], polygon[i + 1], polygon[i]].map(|vertex| welded[vertex]);
+            if repair {
+                let [a, b, c] = triangle;
+                if a == b || b == c || c == a {
+                    report.degenerate_faces += 1;
+                    continue;
+                }
+                let mut corners = triangle;
+                corners.sort();
+                if !seen.insert(corners) {
+                    report.duplicate_faces += 1;
+                    continue;
+                }
+            }
+            triangles.push(triangle);
+        }
+    }
+
+    if repair {
+        report.flipped_faces = orient(&welded_positions, &mut triangles);
+    }
+    let mesh = Mesh::from_triangles_with_boundaries(&welded_positions, &welded_colors, &triangles)?;
+    Ok((mesh, report))
+}
+
+// Turns triangles over so that neighbours agree about which way is out, and so that each
+// connected piece ends up with a positive volume. Returns how many were turned over.
+fn orient(positions: &[Vec3], triangles: &mut [[usize; 3]]) -> usize {
+    let mut edges: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (face, triangle) in triangles.iter().enumerate() {
+        for i in 0..3 {
+            let (from, to) = (triangle[i], triangle[(i + 1) % 3]);
+            edges
+                .entry((from.min(to), from.max(to)))
+                .or_default()
+                .push(face);
+        }
+    }
+    let runs_forward = |triangle: &[usize; 3], from: usize, to: usize| {
+        (0..3).any(|i| triangle[i] == from && triangle[(i + 1) % 3] == to)
+    };
+
+    let mut flipped: Vec<Option<bool>> = vec![None; triangles.len()];
+    for first in 0..triangles.len() {
+        if flipped[first].is_some() {
+            continue;
+        }
+        flipped[first] = Some(false);
+        let mut piece = vec![first];
+        let mut next = 0;
+        while let Some(&face) = piece.get(next) {
+            next += 1;
+            let triangle = triangles[face];
+            for i in 0..3 {
+                let (from, to) = (triangle[i], triangle[(i + 1) % 3]);
+                // Edges shared by more than two faces can't be oriented, building the mesh will
+                // report them
+                let [a, b] = edges[&(from.min(to), from.max(to))][..] else {
+                    continue;
+                };
+                let neighbour = if a == face { b } else { a };
+                if flipped[neighbour].is_none() {
+                    let same_direction = runs_forward(&triangles[neighbour], from, to);
+                    flipped[neighbour] = Some(flipped[face].unwrap() ^ same_direction);
+                    piece.push(neighbour);
+                }
+            }
+        }
+
+        let volume: f32 = piece
+            .iter()
+            .map(|&face| {
+                let [a, b, c] = triangles[face].map(|vertex| positions[vertex]);
+                let volume = a.dot(c.cross(b));
+                if flipped[face].unwrap() {
+                    -volume
+                } else {
+                    volume
+                }
+            })
+            .sum();
+        if volume < 0.0 {
+            for &face in piece.iter() {
+                flipped[face] = flipped[face].map(|flipped| !flipped);
+            }
+        }
+    }
+
+    let mut count = 0;
+    for (triangle, flipped) in triangles.iter_mut().zip(flipped) {
+        if flipped == Some(true) {
+            triangle.swap(1, 2);
+            count += 1;
+        }
+    }
+    count
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::error::MeshError;
+use crate::io::{ImportReport, Welded, build_mesh};
+use crate::math::{Color, Vec3};
+use crate::mesh::Mesh;
+
+pub const MATERIAL_NAME: &str = "gizmo";
+
+/// Writes the mesh as a Wavefront OBJ. Vertex colors use the widely supported `v x y z r g b`
+/// extension, and tied vertices are welded into one.
+///
+/// If `material_library` is given, the OBJ refers to it for its material. write_mtl writes a
+/// matching one.
+pub fn write_obj(
+    mesh: &Mesh,
+    mut writer: impl Write,
+    material_library: Option<&str>,
+) -> io::Result<()> {
+    let welded = Welded::new(mesh);
+
+    writeln!(writer, "# Exported from gizmo")?;
+    if let Some(material_library) = material_library {
+        writeln!(writer, "mtllib {material_library}")?;
+    }
+    writeln!(writer, "o {MATERIAL_NAME}")?;
+    for &slot in welded.vertices.iter() {
+        let position = mesh.positions[slot];
+        let color = mesh.colors[slot];
+        writeln!(
+            writer,
+            "v {} {} {} {} {} {}",
+            position.x, position.y, position.z, color.r, color.g, color.b
+        )?;
+    }
+    if material_library.is_some() {
+        writeln!(writer, "usemtl {MATERIAL_NAME}")?;
+    }
+    for [a, b, c] in welded.triangles(mesh) {
+        // OBJ indices start at 1, and its faces wind counter-clockwise where Godot's wind clockwise
+        writeln!(writer, "f {} {} {}", a + 1, c + 1, b + 1)?;
+    }
+    writer.flush()
+}
+
+/// A plain white material, the color comes from the vertices.
+pub fn write_mtl(mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, "# Exported from gizmo")?;
+    writeln!(writer, "newmtl {MATERIAL_NAME}")?;
+    writeln!(writer, "Ka 0 0 0")?;
+    writeln!(writer, "Kd 1 1 1")?;
+    writeln!(writer, "Ks 0 0 0")?;
+    writeln!(writer, "d 1")?;
+    writeln!(writer, "illum 1")?;
+    writer.flush()
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    /// The line can't be read, counting from 1.
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    /// The faces don't make a mesh gizmo can edit.
+    Mesh(MeshError),
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(error) => write!(f, "{error}"),
+            ObjError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+            ObjError::Mesh(error) => write!(f, "unusable mesh: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+impl From<io::Error> for ObjError {
+    fn from(error: io::Error) -> Self {
+        ObjError::Io(error)
+    }
+}
+
+impl From<MeshError> for ObjError {
+    fn from(error: MeshError) -> Self {
+        ObjError::Mesh(error)
+    }
+}
+
+/// Reads the vertices and faces of a Wavefront OBJ as a single mesh, along with what had to be
+/// fixed to get there. Vertex colors in the `v x y z r g b` extension are kept, everything else
+/// is ignored.
+///
+/// Vertices at the same position are always welded and open edges are left on a boundary.
+/// Collapsed, repeated and inside out faces are only fixed if `repair` is set, otherwise they're
+/// reported as errors.
+pub fn read_obj(reader: impl BufRead, repair: bool) -> Result<(Mesh, ImportReport), ObjError> {
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    let mut polygons = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let syntax_error = |message: String| ObjError::Syntax {
+            line: number + 1,
+            message,
+        };
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("v") => {
+                let values = words
+                    .map(|word| {
+                        word.parse::<f32>()
+                            .map_err(|_| syntax_error(format!("\"{word}\" isn't a number")))
+                    })
+                    .collect::<Result<Vec<f32>, ObjError>>()?;
+                match values[..] {
+                    [x, y, z] | [x, y, z, _] => {
+                        positions.push(Vec3::new(x, y, z));
+                        colors.push(Color::default());
+                    }
+                    [x, y, z, r, g, b] => {
+                        positions.push(Vec3::new(x, y, z));
+                        colors.push(Color::from_rgb(r, g, b));
+                    }
+                    _ => return Err(syntax_error("vertices need 3 coordinates".into())),
+                }
+            }
+            Some("f") => {
+                let polygon = words
+                    .map(|word| {
+                        // Only the position matters out of position/texture/normal
+                        let position = word.split('/').next().unwrap_or_default();
+                        let index: i64 = position
+                            .parse()
+                            .map_err(|_| syntax_error(format!("\"{word}\" isn't a vertex")))?;
+                        // Indices start at 1, negative ones count back from the latest vertex
+                        let resolved = match index {
+                            1.. => index - 1,
+                            ..0 => positions.len() as i64 + index,
+                            0 => -1,
+                        };
+                        if resolved < 0 || resolved >= positions.len() as i64 {
+                            return Err(syntax_error(format!("vertex {index} doesn't exist")));
+                        }
+                        Ok(resolved as usize)
+                    })
+                    .collect::<Result<Vec<usize>, ObjError>>()?;
+                if polygon.len() < 3 {
+                    return Err(syntax_error("faces need at least 3 vertices".into()));
+                }
+                polygons.push(polygon);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(build_mesh(&positions, &colors, &polygons, repair)?)
+}
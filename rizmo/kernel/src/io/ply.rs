@@ -0,0 +1,425 @@
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+use crate::error::MeshError;
+use crate::io::{Welded, build_mesh};
+use crate::math::{Color, Vec3};
+use crate::mesh::Mesh;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io(io::Error),
+    /// The header is malformed or asks for something we can't read.
+    Header(String),
+    /// The data doesn't match what the header describes.
+    Body(String),
+    /// The faces don't make a mesh gizmo can edit.
+    Mesh(MeshError),
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io(error) => write!(f, "{error}"),
+            PlyError::Header(message) => write!(f, "bad PLY header: {message}"),
+            PlyError::Body(message) => write!(f, "bad PLY data: {message}"),
+            PlyError::Mesh(error) => write!(f, "unusable mesh: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {}
+
+impl From<io::Error> for PlyError {
+    fn from(error: io::Error) -> Self {
+        PlyError::Io(error)
+    }
+}
+
+impl From<MeshError> for PlyError {
+    fn from(error: MeshError) -> Self {
+        PlyError::Mesh(error)
+    }
+}
+
+/// Writes the mesh as a PLY with float positions and uchar RGBA colors, welding tied vertices into
+/// one.
+pub fn write_ply(mesh: &Mesh, mut writer: impl Write, format: PlyFormat) -> io::Result<()> {
+    let welded = Welded::new(mesh);
+    // PLY faces wind counter-clockwise
+    let triangles: Vec<[usize; 3]> = welded.triangles(mesh).map(|[a, b, c]| [a, c, b]).collect();
+
+    writeln!(writer, "ply")?;
+    match format {
+        PlyFormat::Ascii => writeln!(writer, "format ascii 1.0")?,
+        PlyFormat::BinaryLittleEndian => writeln!(writer, "format binary_little_endian 1.0")?,
+    }
+    writeln!(writer, "comment Exported from gizmo")?;
+    writeln!(writer, "element vertex {}", welded.vertices.len())?;
+    for property in ["x", "y", "z"] {
+        writeln!(writer, "property float {property}")?;
+    }
+    for property in ["red", "green", "blue", "alpha"] {
+        writeln!(writer, "property uchar {property}")?;
+    }
+    writeln!(writer, "element face {}", triangles.len())?;
+    writeln!(writer, "property list uchar int vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    for &slot in welded.vertices.iter() {
+        let position = mesh.positions[slot];
+        let color = mesh.colors[slot];
+        let channels = [color.r, color.g, color.b, color.a]
+            .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+        match format {
+            PlyFormat::Ascii => writeln!(
+                writer,
+                "{} {} {} {} {} {} {}",
+                position.x,
+                position.y,
+                position.z,
+                channels[0],
+                channels[1],
+                channels[2],
+                channels[3]
+            )?,
+            PlyFormat::BinaryLittleEndian => {
+                for component in [position.x, position.y, position.z] {
+                    writer.write_all(&component.to_le_bytes())?;
+                }
+                writer.write_all(&channels)?;
+            }
+        }
+    }
+    for [a, b, c] in triangles {
+        match format {
+            PlyFormat::Ascii => writeln!(writer, "3 {a} {b} {c}")?,
+            PlyFormat::BinaryLittleEndian => {
+                writer.write_all(&[3])?;
+                for index in [a, b, c] {
+                    writer.write_all(&(index as i32).to_le_bytes())?;
+                }
+            }
+        }
+    }
+    writer.flush()
+}
+
+/// Reads a PLY with `x`, `y` and `z` vertex properties and optional `red`, `green`, `blue` and
+/// `alpha` ones, in ASCII or binary little-endian. Vertices at the same position are welded,
+/// polygons are split into triangles and `connections` is rebuilt, so the result can be edited
+/// straight away. Open edges are left on a boundary.
+pub fn read_ply(mut reader: impl BufRead) -> Result<Mesh, PlyError> {
+    let header = Header::read(&mut reader)?;
+    let mut body = match header.format {
+        PlyFormat::Ascii => {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            Body::Ascii(
+                text.split_ascii_whitespace()
+                    .map(str::to_owned)
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            )
+        }
+        PlyFormat::BinaryLittleEndian => Body::Binary(Box::new(reader)),
+    };
+
+    // Without any color properties the vertices get the default color rather than white
+    let has_colors = header.elements.iter().any(|element| {
+        element.name == "vertex"
+            && element
+                .properties
+                .iter()
+                .any(|property| ["red", "green", "blue", "alpha"].contains(&property.name.as_str()))
+    });
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    let mut polygons = Vec::new();
+    for element in header.elements.iter() {
+        for _ in 0..element.count {
+            let mut position = [0.0; 3];
+            let mut color = [1.0; 4];
+            for property in element.properties.iter() {
+                match property.kind {
+                    PropertyKind::Scalar(scalar) => {
+                        let value = body.scalar(scalar)?;
+                        if element.name != "vertex" {
+                            continue;
+                        }
+                        let channel = |value: f64| match scalar {
+                            // Integer colors run from 0 to the type's maximum
+                            Scalar::U8 => value / 255.0,
+                            Scalar::U16 => value / 65535.0,
+                            _ => value,
+                        };
+                        match property.name.as_str() {
+                            "x" => position[0] = value,
+                            "y" => position[1] = value,
+                            "z" => position[2] = value,
+                            "red" => color[0] = channel(value),
+                            "green" => color[1] = channel(value),
+                            "blue" => color[2] = channel(value),
+                            "alpha" => color[3] = channel(value),
+                            _ => {}
+                        }
+                    }
+                    PropertyKind::List(count, item) => {
+                        // The count isn't trusted for an allocation, a short body stops the
+                        // reading first
+                        let count = whole_number(body.scalar(count)?, "list length")?;
+                        let mut polygon = Vec::new();
+                        for _ in 0..count {
+                            polygon.push(body.scalar(item)?);
+                        }
+                        if element.name != "face" || !is_face_list(&property.name) {
+                            continue;
+                        }
+                        polygons.push(
+                            polygon
+                                .into_iter()
+                                .map(|index| whole_number(index, "vertex index"))
+                                .collect::<Result<Vec<usize>, _>>()?,
+                        );
+                    }
+                }
+            }
+            if element.name == "vertex" {
+                positions.push(Vec3::new(
+                    position[0] as f32,
+                    position[1] as f32,
+                    position[2] as f32,
+                ));
+                if has_colors {
+                    colors.push(Color::from_rgba(
+                        color[0] as f32,
+                        color[1] as f32,
+                        color[2] as f32,
+                        color[3] as f32,
+                    ));
+                }
+            }
+        }
+    }
+
+    // Exporters split vertices wherever the color changes, so they're welded back together like
+    // OBJ ones, keeping the first color
+    let (mesh, _) = build_mesh(&positions, &colors, &polygons, false)?;
+    Ok(mesh)
+}
+
+// Counts and indices are read like any other value, so they may be negative or fractional
+fn whole_number(value: f64, what: &str) -> Result<usize, PlyError> {
+    // The fractional part of NaN or infinity is NaN, so those are caught too
+    if value < 0.0 || value.fract() != 0.0 {
+        return Err(PlyError::Body(format!(
+            "{what} {value} isn't a whole number"
+        )));
+    }
+    Ok(value as usize)
+}
+
+fn is_face_list(name: &str) -> bool {
+    name == "vertex_indices" || name == "vertex_index"
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        match name {
+            "char" | "int8" => Some(Scalar::I8),
+            "uchar" | "uint8" => Some(Scalar::U8),
+            "short" | "int16" => Some(Scalar::I16),
+            "ushort" | "uint16" => Some(Scalar::U16),
+            "int" | "int32" => Some(Scalar::I32),
+            "uint" | "uint32" => Some(Scalar::U32),
+            "float" | "float32" => Some(Scalar::F32),
+            "double" | "float64" => Some(Scalar::F64),
+            _ => None,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    fn decode_le(&self, bytes: &[u8]) -> f64 {
+        match self {
+            Scalar::I8 => bytes[0] as i8 as f64,
+            Scalar::U8 => bytes[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            Scalar::U32 => u32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            Scalar::F32 => f32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            Scalar::F64 => f64::from_le_bytes(bytes[..8].try_into().unwrap()),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum PropertyKind {
+    Scalar(Scalar),
+    /// The type of the length, then the type of the items.
+    List(Scalar, Scalar),
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: PlyFormat,
+    elements: Vec<Element>,
+}
+
+impl Header {
+    fn read(reader: &mut impl BufRead) -> Result<Header, PlyError> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(PlyError::Header("missing end_header".into()));
+            }
+            let line = line.trim().to_owned();
+            if line == "end_header" {
+                break;
+            }
+            lines.push(line);
+        }
+
+        let mut lines = lines.into_iter();
+        if lines.next().as_deref() != Some("ply") {
+            return Err(PlyError::Header("not a PLY file".into()));
+        }
+        let mut format = None;
+        let mut elements: Vec<Element> = Vec::new();
+        for line in lines {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [] | ["comment", ..] | ["obj_info", ..] => {}
+                ["format", "ascii", "1.0"] => format = Some(PlyFormat::Ascii),
+                ["format", "binary_little_endian", "1.0"] => {
+                    format = Some(PlyFormat::BinaryLittleEndian)
+                }
+                ["format", other, ..] => {
+                    return Err(PlyError::Header(format!("unsupported format {other}")));
+                }
+                ["element", name, count] => elements.push(Element {
+                    name: name.to_string(),
+                    count: count
+                        .parse()
+                        .map_err(|_| PlyError::Header(format!("bad element count {count}")))?,
+                    properties: Vec::new(),
+                }),
+                ["property", "list", count, item, name] => {
+                    let kind = PropertyKind::List(parse_scalar(count)?, parse_scalar(item)?);
+                    add_property(&mut elements, name, kind)?;
+                }
+                ["property", scalar, name] => {
+                    let kind = PropertyKind::Scalar(parse_scalar(scalar)?);
+                    add_property(&mut elements, name, kind)?;
+                }
+                _ => return Err(PlyError::Header(format!("can't read \"{line}\""))),
+            }
+        }
+
+        let format = format.ok_or(PlyError::Header("missing format".into()))?;
+        let vertex = elements.iter().find(|element| element.name == "vertex");
+        let has_positions = vertex.is_some_and(|vertex| {
+            ["x", "y", "z"].iter().all(|axis| {
+                vertex.properties.iter().any(|property| {
+                    property.name == *axis && matches!(property.kind, PropertyKind::Scalar(_))
+                })
+            })
+        });
+        if !has_positions {
+            return Err(PlyError::Header("vertices need x, y and z".into()));
+        }
+        let has_faces = elements.iter().any(|element| {
+            element.name == "face"
+                && element.properties.iter().any(|property| {
+                    is_face_list(&property.name) && matches!(property.kind, PropertyKind::List(..))
+                })
+        });
+        if !has_faces {
+            return Err(PlyError::Header("faces need a vertex_indices list".into()));
+        }
+        Ok(Header { format, elements })
+    }
+}
+
+fn parse_scalar(name: &str) -> Result<Scalar, PlyError> {
+    Scalar::parse(name).ok_or_else(|| PlyError::Header(format!("unknown type {name}")))
+}
+
+fn add_property(elements: &mut [Element], name: &str, kind: PropertyKind) -> Result<(), PlyError> {
+    let element = elements
+        .last_mut()
+        .ok_or_else(|| PlyError::Header(format!("property {name} isn't in an element")))?;
+    element.properties.push(Property {
+        name: name.to_string(),
+        kind,
+    });
+    Ok(())
+}
+
+enum Body<'a> {
+    Ascii(std::vec::IntoIter<String>),
+    Binary(Box<dyn Read + 'a>),
+}
+
+impl Body<'_> {
+    fn scalar(&mut self, scalar: Scalar) -> Result<f64, PlyError> {
+        match self {
+            Body::Ascii(words) => {
+                let word = words
+                    .next()
+                    .ok_or_else(|| PlyError::Body("ran out of values".into()))?;
+                word.parse()
+                    .map_err(|_| PlyError::Body(format!("\"{word}\" isn't a number")))
+            }
+            Body::Binary(reader) => {
+                let mut bytes = [0; 8];
+                reader
+                    .read_exact(&mut bytes[..scalar.size()])
+                    .map_err(|error| match error.kind() {
+                        io::ErrorKind::UnexpectedEof => PlyError::Body("ran out of values".into()),
+                        _ => PlyError::Io(error),
+                    })?;
+                Ok(scalar.decode_le(&bytes))
+            }
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::math::Vec3;
+use crate::mesh::{Mesh, decompose_meta_index};
+
+/// Something that would stop the mesh from printing as a closed solid.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WatertightIssue {
+    /// The half-edge has no proper twin, so the surface has a hole along it.
+    OpenEdge { meta_index: usize },
+    /// More than two faces meet along the edge between these vertices.
+    NonManifoldEdge { from: usize, to: usize },
+    /// The face points into the solid instead of out of it.
+    InvertedFace { face: usize },
+}
+
+impl fmt::Display for WatertightIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatertightIssue::OpenEdge { meta_index } => {
+                write!(f, "half-edge {meta_index} is open")
+            }
+            WatertightIssue::NonManifoldEdge { from, to } => {
+                write!(
+                    f,
+                    "more than two faces meet at the edge from {from} to {to}"
+                )
+            }
+            WatertightIssue::InvertedFace { face } => write!(f, "face {face} is inside out"),
+        }
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct WatertightReport {
+    pub issues: Vec<WatertightIssue>,
+}
+
+impl WatertightReport {
+    pub fn is_watertight(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for WatertightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_watertight() {
+            return write!(f, "the mesh is watertight");
+        }
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that the mesh encloses a solid, which slicers need to print it.
+///
+/// Edges are followed through `connections`, so a half-edge whose twin is missing or doesn't run
+/// between the same vertices counts as open. Faces are inverted when they disagree with their
+/// neighbours about which way is out, or when their whole piece of the mesh is inside out.
+pub fn check_watertight(mesh: &Mesh) -> WatertightReport {
+    let edge = |meta_index: usize| {
+        let (start, offset) = decompose_meta_index(meta_index);
+        (
+            mesh.indices[meta_index],
+            mesh.indices[start + (offset + 1) % 3],
+        )
+    };
+    let mut issues = Vec::new();
+
+    // Every half-edge with a twin that closes it, and whether the two run the same way
+    let mut twins = vec![None; mesh.connections.len()];
+    for (meta_index, &connection) in mesh.connections.iter().enumerate() {
+        let twin = connection as usize;
+        let closed = connection >= 0
+            && twin < mesh.connections.len()
+            && mesh.connections[twin] as usize == meta_index
+            && decompose_meta_index(twin).0 != decompose_meta_index(meta_index).0;
+        let (from, to) = edge(meta_index);
+        match closed.then(|| edge(twin)) {
+            Some(twin_edge) if twin_edge == (to, from) => twins[meta_index] = Some((twin, false)),
+            Some(twin_edge) if twin_edge == (from, to) => twins[meta_index] = Some((twin, true)),
+            _ => issues.push(WatertightIssue::OpenEdge { meta_index }),
+        }
+    }
+
+    let mut edge_faces: HashMap<(i32, i32), usize> = HashMap::new();
+    for meta_index in 0..mesh.indices.len() {
+        let (from, to) = edge(meta_index);
+        *edge_faces.entry((from.min(to), from.max(to))).or_default() += 1;
+    }
+    let mut non_manifold: Vec<(i32, i32)> = edge_faces
+        .into_iter()
+        .filter(|&(_, faces)| faces > 2)
+        .map(|(edge, _)| edge)
+        .collect();
+    non_manifold.sort();
+    issues.extend(
+        non_manifold
+            .into_iter()
+            .map(|(from, to)| WatertightIssue::NonManifoldEdge {
+                from: from as usize,
+                to: to as usize,
+            }),
+    );
+
+    // Spread the orientation of one face over each connected piece, then flip the whole piece
+    // over if that leaves it with a negative volume
+    let mut flipped: Vec<Option<bool>> = vec![None; mesh.face_count()];
+    for first in 0..mesh.face_count() {
+        if flipped[first].is_some() {
+            continue;
+        }
+        flipped[first] = Some(false);
+        let mut piece = vec![first];
+        let mut next = 0;
+        while let Some(&face) = piece.get(next) {
+            next += 1;
+            for &(twin, same_direction) in twins[face * 3..face * 3 + 3].iter().flatten() {
+                let neighbour = decompose_meta_index(twin).0 / 3;
+                if flipped[neighbour].is_none() {
+                    flipped[neighbour] = Some(flipped[face].unwrap() ^ same_direction);
+                    piece.push(neighbour);
+                }
+            }
+        }
+
+        let volume: f32 = piece
+            .iter()
+            .map(|&face| {
+                let [a, b, c] =
+                    [0, 1, 2].map(|i| mesh.positions[mesh.indices[face * 3 + i] as usize]);
+                let volume = a.dot(c.cross(b));
+                if flipped[face].unwrap() {
+                    -volume
+                } else {
+                    volume
+                }
+            })
+            .sum();
+        if volume < 0.0 {
+            for &face in piece.iter() {
+                flipped[face] = flipped[face].map(|flipped| !flipped);
+            }
+        }
+    }
+    issues.extend(
+        flipped
+            .iter()
+            .enumerate()
+            .filter(|(_, flipped)| **flipped == Some(true))
+            .map(|(face, _)| WatertightIssue::InvertedFace { face }),
+    );
+
+    WatertightReport { issues }
+}
+
+/// Writes the mesh as a binary STL with a normal for every face.
+pub fn write_stl_binary(mesh: &Mesh, mut writer: impl Write) -> io::Result<()> {
+    let mut header = [0u8; 80];
+    let title = b"Exported from gizmo";
+    header[..title.len()].copy_from_slice(title);
+    writer.write_all(&header)?;
+    writer.write_all(&(mesh.face_count() as u32).to_le_bytes())?;
+    for face in 0..mesh.face_count() {
+        let (normal, corners) = facet(mesh, face);
+        for v in std::iter::once(normal).chain(corners) {
+            for component in [v.x, v.y, v.z] {
+                writer.write_all(&component.to_le_bytes())?;
+            }
+        }
+        // Attribute byte count, which nothing uses
+        writer.write_all(&0u16.to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+/// Writes the mesh as an ASCII STL with a normal for every face.
+pub fn write_stl_ascii(mesh: &Mesh, mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, "solid gizmo")?;
+    for face in 0..mesh.face_count() {
+        let (normal, corners) = facet(mesh, face);
+        writeln!(
+            writer,
+            "  facet normal {} {} {}",
+            normal.x, normal.y, normal.z
+        )?;
+        writeln!(writer, "    outer loop")?;
+        for corner in corners {
+            writeln!(
+                writer,
+                "      vertex {} {} {}",
+                corner.x, corner.y, corner.z
+            )?;
+        }
+        writeln!(writer, "    endloop")?;
+        writeln!(writer, "  endfacet")?;
+    }
+    writeln!(writer, "endsolid gizmo")?;
+    writer.flush()
+}
+
+// The face's normal and corners, wound counter-clockwise the way STL expects
+fn facet(mesh: &Mesh, face: usize) -> (Vec3, [Vec3; 3]) {
+    let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[mesh.indices[face * 3 + i] as usize]);
+    (mesh.face_normal(face * 3), [a, c, b])
+}
@@ -0,0 +1,22 @@
+//! The geometry behind gizmo, free of any Godot types so it can be tested and reused headlessly.
+
+pub mod command;
+pub mod document;
+pub mod error;
+pub mod io;
+pub mod math;
+pub mod mesh;
+pub mod primitive;
+pub mod script;
+pub mod stack;
+pub mod validate;
+
+pub use command::{Command, Pivot, Smoothing, Space, Subdivision, Target};
+pub use document::Document;
+pub use error::{CommandError, DocumentError, MeshError, ReplayError};
+pub use math::{Color, Vec3};
+pub use mesh::{Mesh, MetaIndexId, decompose_meta_index, next_corner, previous_corner};
+pub use primitive::Primitive;
+pub use script::{ScriptError, parse_script, print_script};
+pub use stack::{CommandId, CommandStack, FinishLine};
+pub use validate::{TopologyIssue, ValidationReport};
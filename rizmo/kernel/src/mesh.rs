@@ -0,0 +1,472 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::error::{CommandError, MeshError};
+use crate::math::{Color, Vec3};
+
+pub type MetaIndexId = i32;
+
+// A triangle mesh stored as flat arrays so it can be handed to a renderer as-is.
+//
+// Every corner of every triangle has a "meta index" into `indices`. The corner at meta index `i`
+// also names the half-edge running from `indices[i]` to the next corner of the same face, and
+// `connections[i]` is the meta index of the twin half-edge in the neighbouring face, or
+// `Mesh::BOUNDARY` if there is no face on the other side.
+//
+// Vertex storage is overestimated in blocks of BLOCK_SIZE so that renderers can allocate once and
+// update regions afterwards. Only the first `vertex_count()` positions are live.
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    pub positions: Vec<Vec3>,
+    pub colors: Vec<Color>,
+    deleted_vertices: Vec<usize>,
+    pub indices: Vec<i32>,
+    pub connections: Vec<i32>,
+    index: usize,
+    pub tracked_indices: HashMap<MetaIndexId, i32>,
+    last_meta_index_id: MetaIndexId,
+}
+
+impl Default for Mesh {
+    fn default() -> Self {
+        Mesh::new()
+    }
+}
+
+impl Mesh {
+    pub const BLOCK_SIZE: usize = 64;
+    /// The connection of a half-edge on the edge of a hole, which has no twin.
+    pub const BOUNDARY: i32 = -1;
+
+    pub fn new() -> Self {
+        let mut mesh = Mesh {
+            positions: Vec::new(),
+            colors: Vec::new(),
+            deleted_vertices: Vec::new(),
+            indices: Vec::new(),
+            connections: Vec::new(),
+            index: 0,
+            tracked_indices: HashMap::new(),
+            last_meta_index_id: 0,
+        };
+        mesh.clear();
+        mesh
+    }
+
+    /// Builds a mesh from triangles wound clockwise, the same as Godot, working out `connections`
+    /// by pairing every half-edge with the one running the other way along the same edge.
+    ///
+    /// Vertices no triangle uses are dropped, and vertices without a color get the default one.
+    /// The triangles have to close up into a manifold surface, since every half-edge needs a twin.
+    pub fn from_triangles(
+        positions: &[Vec3],
+        colors: &[Color],
+        triangles: &[[usize; 3]],
+    ) -> Result<Mesh, MeshError> {
+        Mesh::build(positions, colors, triangles, false)
+    }
+
+    /// Builds a mesh the same way as `from_triangles`, except that half-edges without a twin are
+    /// left on a boundary instead of being an error. Edges with more than two faces are still
+    /// refused.
+    pub fn from_triangles_with_boundaries(
+        positions: &[Vec3],
+        colors: &[Color],
+        triangles: &[[usize; 3]],
+    ) -> Result<Mesh, MeshError> {
+        Mesh::build(positions, colors, triangles, true)
+    }
+
+    fn build(
+        positions: &[Vec3],
+        colors: &[Color],
+        triangles: &[[usize; 3]],
+        allow_boundaries: bool,
+    ) -> Result<Mesh, MeshError> {
+        if triangles.is_empty() {
+            return Err(MeshError::NoFaces);
+        }
+
+        let mut remap = vec![None; positions.len()];
+        let mut used = Vec::new();
+        let mut indices = Vec::with_capacity(triangles.len() * 3);
+        for (face, triangle) in triangles.iter().enumerate() {
+            if triangle[0] == triangle[1]
+                || triangle[1] == triangle[2]
+                || triangle[2] == triangle[0]
+            {
+                return Err(MeshError::DegenerateFace { face });
+            }
+            for &vertex in triangle {
+                let slot = remap
+                    .get_mut(vertex)
+                    .ok_or(MeshError::VertexOutOfRange { face, vertex })?;
+                let index = *slot.get_or_insert_with(|| {
+                    used.push(vertex);
+                    used.len() - 1
+                });
+                indices.push(index as i32);
+            }
+        }
+
+        let mut half_edges = HashMap::new();
+        for meta_index in 0..indices.len() {
+            let (start, offset) = decompose_meta_index(meta_index);
+            let edge = (indices[meta_index], indices[start + (offset + 1) % 3]);
+            if half_edges.insert(edge, meta_index).is_some() {
+                return Err(MeshError::NonManifoldEdge {
+                    from: used[edge.0 as usize],
+                    to: used[edge.1 as usize],
+                });
+            }
+        }
+        let mut connections = vec![0; indices.len()];
+        for (&(from, to), &meta_index) in half_edges.iter() {
+            connections[meta_index] = match half_edges.get(&(to, from)) {
+                Some(&twin) => twin as i32,
+                None if allow_boundaries => Mesh::BOUNDARY,
+                None => {
+                    return Err(MeshError::OpenEdge {
+                        from: used[from as usize],
+                        to: used[to as usize],
+                    });
+                }
+            };
+        }
+
+        let size = used.len().next_multiple_of(Mesh::BLOCK_SIZE);
+        let mut mesh_positions: Vec<Vec3> = used.iter().map(|&vertex| positions[vertex]).collect();
+        mesh_positions.resize(size, Vec3::ZERO);
+        let mut mesh_colors: Vec<Color> = used
+            .iter()
+            .map(|&vertex| colors.get(vertex).copied().unwrap_or_default())
+            .collect();
+        mesh_colors.resize(size, Color::default());
+
+        let mesh = Mesh {
+            positions: mesh_positions,
+            colors: mesh_colors,
+            deleted_vertices: Vec::new(),
+            indices,
+            connections,
+            index: used.len(),
+            tracked_indices: HashMap::new(),
+            last_meta_index_id: 0,
+        };
+        let report = mesh.validate();
+        if !report.is_valid() {
+            return Err(MeshError::InvalidTopology(report));
+        }
+        Ok(mesh)
+    }
+
+    pub fn clear(&mut self) {
+        self.positions.resize(Mesh::BLOCK_SIZE, Vec3::ZERO);
+        self.positions[0..4].copy_from_slice(&[
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        ]);
+        self.colors.clear();
+        self.colors.resize(Mesh::BLOCK_SIZE, Color::default());
+
+        self.indices = vec![0, 2, 1, 0, 1, 3, 0, 3, 2, 3, 1, 2];
+        self.connections = vec![8, 10, 3, 2, 9, 6, 5, 11, 0, 4, 1, 7];
+        self.index = 4;
+        self.deleted_vertices.clear();
+        self.tracked_indices.clear();
+        self.last_meta_index_id = 0;
+    }
+
+    /// The number of vertices in use. Everything in `positions` past this is spare capacity.
+    pub fn vertex_count(&self) -> usize {
+        self.index
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// The unit normal of the face with a corner at `meta_index`, or zero if the face has no area.
+    /// Faces wind clockwise when seen from the front, the same as in Godot.
+    pub fn face_normal(&self, meta_index: usize) -> Vec3 {
+        let (start, _) = decompose_meta_index(meta_index);
+        let [a, b, c] = [0, 1, 2].map(|i| self.positions[self.indices[start + i] as usize]);
+        (c - a).cross(b - a).normalized()
+    }
+
+    /// Vertices that are no longer used and are waiting for `clean` to reclaim them.
+    pub fn deleted_vertices(&self) -> &[usize] {
+        &self.deleted_vertices
+    }
+
+    /// Packs the live vertices at the front again by moving the last ones into the slots of
+    /// deleted vertices, updating `indices` to match.
+    pub fn clean(&mut self) {
+        if self.deleted_vertices.is_empty() {
+            return;
+        }
+        let deleted: BTreeSet<usize> = self.deleted_vertices.drain(..).collect();
+        let mut remap: Vec<usize> = (0..self.index).collect();
+        let mut end = self.index;
+        for &slot in &deleted {
+            // Deleted vertices at the end just go
+            while end > 0 && deleted.contains(&(end - 1)) {
+                end -= 1;
+            }
+            if slot >= end {
+                break;
+            }
+            end -= 1;
+            self.positions[slot] = self.positions[end];
+            self.colors[slot] = self.colors[end];
+            remap[end] = slot;
+        }
+        for index in self.indices.iter_mut() {
+            *index = remap[*index as usize] as i32;
+        }
+
+        self.index = end;
+        let new_size = end.next_multiple_of(Mesh::BLOCK_SIZE);
+        self.positions.resize(new_size, Vec3::ZERO);
+        self.colors.resize(new_size, Color::default());
+    }
+
+    pub fn request_more_memory(&mut self) {
+        let new_size = self.positions.len() + Mesh::BLOCK_SIZE;
+        self.positions.resize(new_size, Vec3::ZERO);
+        self.colors.resize(new_size, Color::default());
+    }
+
+    pub fn add_vertex(&mut self, position: Vec3) -> usize {
+        if self.index.is_multiple_of(Mesh::BLOCK_SIZE) {
+            self.request_more_memory();
+        }
+
+        let index = self.index;
+        self.positions[index] = position;
+        self.index += 1;
+        index
+    }
+
+    /// Appends two faces. Any connection that points at an existing half-edge is mirrored so that
+    /// the existing half-edge points back at the new one.
+    pub fn add_faces(&mut self, indices: [i32; 6], connections: [i32; 6]) {
+        self.append_faces(&indices, &connections);
+    }
+
+    /// Appends one face, mirroring its connections the same way as `add_faces`.
+    pub fn add_face(&mut self, indices: [i32; 3], connections: [i32; 3]) {
+        self.append_faces(&indices, &connections);
+    }
+
+    fn append_faces(&mut self, indices: &[i32], connections: &[i32]) {
+        self.indices.extend_from_slice(indices);
+
+        let connections_length = self.connections.len();
+        self.connections.extend_from_slice(connections);
+
+        for (offset, connection) in connections.iter().enumerate() {
+            if *connection >= connections_length as i32 || *connection == Mesh::BOUNDARY {
+                continue;
+            }
+            self.connections[*connection as usize] = (connections_length + offset) as i32;
+        }
+    }
+
+    /// The twin of the half-edge at `meta_index`, or None on a boundary.
+    pub fn twin(&self, meta_index: usize) -> Option<usize> {
+        match self.connections[meta_index] {
+            Mesh::BOUNDARY => None,
+            twin => Some(twin as usize),
+        }
+    }
+
+    /// Removes the face starting at `start` by moving the last face into its place, updating the
+    /// twins and tracked indices of the moved face.
+    ///
+    /// Nothing should connect to the removed face anymore, and selections on it should already
+    /// have been moved elsewhere.
+    pub fn remove_face(&mut self, start: usize) {
+        let last = self.indices.len() - 3;
+        if start != last {
+            for offset in 0..3 {
+                let (from, to) = (last + offset, start + offset);
+                self.indices[to] = self.indices[from];
+                let twin = self.connections[from];
+                self.connections[to] = twin;
+                if twin != Mesh::BOUNDARY {
+                    self.connections[twin as usize] = to as i32;
+                }
+                for meta_index in self.tracked_indices.values_mut() {
+                    if *meta_index == from as i32 {
+                        *meta_index = to as i32;
+                    }
+                }
+            }
+        }
+        self.indices.truncate(last);
+        self.connections.truncate(last);
+    }
+
+    /// Marks a vertex no face uses anymore as free, for `clean` to reclaim.
+    pub fn delete_vertex(&mut self, vertex: usize) {
+        self.deleted_vertices.push(vertex);
+    }
+
+    pub fn track_index(&mut self, meta_index: i32) -> Result<MetaIndexId, CommandError> {
+        if meta_index < 0 || meta_index >= self.indices.len() as i32 {
+            return Err(CommandError::MetaIndexOutOfRange(meta_index));
+        }
+
+        let new_meta_index_id = self.last_meta_index_id;
+        self.last_meta_index_id += 1;
+        self.tracked_indices.insert(new_meta_index_id, meta_index);
+
+        Ok(new_meta_index_id)
+    }
+
+    /// Moves the selection across its half-edge to the twin. Fails on a boundary, where there's
+    /// no face to move to.
+    pub fn traverse_connection(&mut self, meta_index_id: MetaIndexId) -> Result<(), CommandError> {
+        let meta_index = self.get_meta_index(meta_index_id)?;
+        let twin = self
+            .twin(meta_index as usize)
+            .ok_or(CommandError::BoundaryEdge(meta_index))?;
+        self.tracked_indices.insert(meta_index_id, twin as i32);
+        Ok(())
+    }
+
+    /// The boundary half-edge that follows the one at `meta_index` around its hole, found by
+    /// turning around the vertex it ends at until there's no face on the other side.
+    ///
+    /// Returns None if the half-edge isn't on a boundary, or if the walk never gets anywhere.
+    pub fn next_boundary(&self, meta_index: usize) -> Option<usize> {
+        if self.twin(meta_index).is_some() {
+            return None;
+        }
+        let mut corner = next_corner(meta_index);
+        for _ in 0..self.indices.len() {
+            match self.twin(corner) {
+                None => return Some(corner),
+                Some(twin) => corner = next_corner(twin),
+            }
+        }
+        None
+    }
+
+    /// Every half-edge around the hole the boundary half-edge at `meta_index` is on, in order and
+    /// starting with it.
+    pub fn boundary_loop(&self, meta_index: usize) -> Option<Vec<usize>> {
+        let mut boundary = vec![meta_index];
+        loop {
+            let next = self.next_boundary(*boundary.last()?)?;
+            if next == meta_index {
+                return Some(boundary);
+            }
+            if boundary.len() >= self.indices.len() {
+                return None;
+            }
+            boundary.push(next);
+        }
+    }
+
+    /// The tracked meta index, which is always a valid index into `indices`.
+    pub fn get_meta_index(&self, meta_index_id: MetaIndexId) -> Result<i32, CommandError> {
+        let meta_index = *self
+            .tracked_indices
+            .get(&meta_index_id)
+            .ok_or(CommandError::UntrackedSelection(meta_index_id))?;
+        if meta_index < 0 || meta_index >= self.indices.len() as i32 {
+            return Err(CommandError::MetaIndexOutOfRange(meta_index));
+        }
+        Ok(meta_index)
+    }
+
+    /// The positions of the tracked face, starting at the tracked corner.
+    pub fn get_face_positions(
+        &self,
+        meta_index_id: MetaIndexId,
+    ) -> Result<[Vec3; 3], CommandError> {
+        let (start, offset) = decompose_meta_index(self.get_meta_index(meta_index_id)? as usize);
+        let mut indices = [0; 3];
+        indices.copy_from_slice(&self.indices[start..start + 3]);
+        indices.rotate_left(offset);
+        Ok(indices.map(|index| self.positions[index as usize]))
+    }
+
+    pub fn modify_vertex(
+        &mut self,
+        meta_index_id: MetaIndexId,
+        position: Vec3,
+    ) -> Result<(), CommandError> {
+        // It's not enough to update a single vertex
+        // Some vertices are "tied," they have the same position but different attributes
+        // This algorithm navigates all tied vertices and updates them.
+        let meta_index = self.get_meta_index(meta_index_id)?;
+        let fan = self
+            .vertex_fan(meta_index as usize)
+            .ok_or(CommandError::BrokenVertexFan(meta_index))?;
+        for corner in fan {
+            self.positions[self.indices[corner] as usize] = position;
+        }
+        Ok(())
+    }
+
+    /// The corners that share a vertex with `meta_index`, found by crossing to the twin half-edge
+    /// and stepping to the next corner until we arrive where we started.
+    ///
+    /// A vertex on a boundary has no way round, so the walk also goes the other way from
+    /// `meta_index` and the fan runs from one side of the hole to the other.
+    ///
+    /// Returns None if the walk never ends, which only happens when `connections` is broken.
+    pub fn vertex_fan(&self, meta_index: usize) -> Option<Vec<usize>> {
+        let mut fan = vec![meta_index];
+        let mut next_meta_index = meta_index;
+        loop {
+            let twin = *self.connections.get(next_meta_index)?;
+            if twin == Mesh::BOUNDARY {
+                break;
+            }
+            next_meta_index = next_corner(twin as usize);
+            if next_meta_index == meta_index {
+                return Some(fan);
+            }
+            if fan.len() >= self.indices.len() || next_meta_index >= self.indices.len() {
+                return None;
+            }
+            fan.push(next_meta_index);
+        }
+
+        let mut previous_meta_index = meta_index;
+        loop {
+            let twin = *self.connections.get(previous_corner(previous_meta_index))?;
+            if twin == Mesh::BOUNDARY {
+                return Some(fan);
+            }
+            previous_meta_index = twin as usize;
+            if fan.len() >= self.indices.len() || previous_meta_index >= self.indices.len() {
+                return None;
+            }
+            fan.insert(0, previous_meta_index);
+        }
+    }
+}
+
+pub fn decompose_meta_index(meta_index: usize) -> (usize, usize) {
+    let offset = meta_index % 3;
+    let start = meta_index - offset;
+    (start, offset)
+}
+
+/// The next corner of the same face, which is also where the half-edge at `meta_index` ends.
+pub fn next_corner(meta_index: usize) -> usize {
+    let (start, offset) = decompose_meta_index(meta_index);
+    start + (offset + 1) % 3
+}
+
+/// The previous corner of the same face, where the half-edge ending at `meta_index` starts.
+pub fn previous_corner(meta_index: usize) -> usize {
+    let (start, offset) = decompose_meta_index(meta_index);
+    start + (offset + 2) % 3
+}
@@ -0,0 +1,356 @@
+//! Closed meshes a model can start from instead of the tetrahedron.
+//!
+//! Every primitive fits in a box one unit across, centered on the origin, except the tetrahedron
+//! which keeps its corner at the origin.
+
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::Vec3;
+use crate::mesh::Mesh;
+
+/// A mesh to start from and what shapes it. Counts below the smallest that still closes up, such as
+/// a cylinder with two sides, are raised to it when building the mesh, and counts above
+/// `MAX_SEGMENTS` are lowered to it.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+pub enum Primitive {
+    #[default]
+    Tetrahedron,
+    Cube,
+    Octahedron,
+    /// An icosahedron with every face split into four `subdivisions` times, pushed out onto the
+    /// sphere.
+    Icosphere {
+        subdivisions: u32,
+    },
+    /// A sphere made of `segments` slices around the y axis and `rings` bands from pole to pole.
+    UvSphere {
+        segments: u32,
+        rings: u32,
+    },
+    /// A cylinder along the y axis with `segments` sides.
+    Cylinder {
+        segments: u32,
+    },
+    /// A cone pointing up the y axis with `segments` sides.
+    Cone {
+        segments: u32,
+    },
+    /// A ring around the y axis, `segments` around the ring and `sides` around the tube, with the
+    /// tube's radius as a fraction of the ring's. The thickness is kept between
+    /// `MIN_TORUS_THICKNESS` and `MAX_TORUS_THICKNESS` when building the mesh, and one that isn't a
+    /// number is taken as 0.5.
+    Torus {
+        segments: u32,
+        sides: u32,
+        thickness: f32,
+    },
+}
+
+// Every level quadruples the face count, the same as Subdivide
+const MAX_ICOSPHERE_SUBDIVISIONS: u32 = 6;
+
+/// The most segments, rings or sides a primitive is built with. A torus at this many segments and
+/// sides already has over a hundred thousand faces.
+pub const MAX_SEGMENTS: u32 = 256;
+
+/// The thinnest a torus's tube can be, as a fraction of the ring's radius.
+pub const MIN_TORUS_THICKNESS: f32 = 0.01;
+
+/// The thickest a torus's tube can be. At 1 the tube would close up the hole in the middle.
+pub const MAX_TORUS_THICKNESS: f32 = 0.9;
+
+impl Primitive {
+    /// Each kind of primitive with the parameters it gets when nothing else is said.
+    pub const ALL: [Primitive; 8] = [
+        Primitive::Tetrahedron,
+        Primitive::Cube,
+        Primitive::Octahedron,
+        Primitive::Icosphere { subdivisions: 2 },
+        Primitive::UvSphere {
+            segments: 16,
+            rings: 8,
+        },
+        Primitive::Cylinder { segments: 16 },
+        Primitive::Cone { segments: 16 },
+        Primitive::Torus {
+            segments: 24,
+            sides: 12,
+            thickness: 0.5,
+        },
+    ];
+
+    /// The short name used in the editor.
+    pub fn name(self) -> &'static str {
+        match self {
+            Primitive::Tetrahedron => "tetrahedron",
+            Primitive::Cube => "cube",
+            Primitive::Octahedron => "octahedron",
+            Primitive::Icosphere { .. } => "icosphere",
+            Primitive::UvSphere { .. } => "uv_sphere",
+            Primitive::Cylinder { .. } => "cylinder",
+            Primitive::Cone { .. } => "cone",
+            Primitive::Torus { .. } => "torus",
+        }
+    }
+
+    /// The primitive with this name and its default parameters.
+    pub fn from_name(name: &str) -> Option<Primitive> {
+        Primitive::ALL
+            .into_iter()
+            .find(|primitive| primitive.name() == name)
+    }
+
+    pub fn to_mesh(self) -> Mesh {
+        let (positions, triangles) = match self {
+            Primitive::Tetrahedron => return Mesh::new(),
+            Primitive::Cube => cube(),
+            Primitive::Octahedron => octahedron(),
+            Primitive::Icosphere { subdivisions } => {
+                icosphere(subdivisions.min(MAX_ICOSPHERE_SUBDIVISIONS))
+            }
+            Primitive::UvSphere { segments, rings } => {
+                uv_sphere(count(segments, 3), count(rings, 2))
+            }
+            Primitive::Cylinder { segments } => cylinder(count(segments, 3)),
+            Primitive::Cone { segments } => cone(count(segments, 3)),
+            Primitive::Torus {
+                segments,
+                sides,
+                thickness,
+            } => {
+                let thickness = match thickness.is_nan() {
+                    true => 0.5,
+                    false => thickness.clamp(MIN_TORUS_THICKNESS, MAX_TORUS_THICKNESS),
+                };
+                torus(count(segments, 3), count(sides, 3), thickness)
+            }
+        };
+        // The generators wind counter-clockwise seen from outside, Godot wants clockwise
+        let triangles: Vec<[usize; 3]> = triangles.iter().map(|&[a, b, c]| [a, c, b]).collect();
+        Mesh::from_triangles(&positions, &[], &triangles).expect("primitives are closed")
+    }
+}
+
+type Triangles = (Vec<Vec3>, Vec<[usize; 3]>);
+
+// A segment, ring or side count between the fewest that close up and MAX_SEGMENTS
+fn count(count: u32, fewest: u32) -> usize {
+    count.clamp(fewest, MAX_SEGMENTS) as usize
+}
+
+// Splits a quad counter-clockwise from a to d into two triangles
+fn quad(triangles: &mut Vec<[usize; 3]>, [a, b, c, d]: [usize; 4]) {
+    triangles.push([a, b, c]);
+    triangles.push([a, c, d]);
+}
+
+fn cube() -> Triangles {
+    let positions = (0..8)
+        .map(|corner| {
+            let [x, y, z] = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            Vec3::new(x as f32 - 0.5, y as f32 - 0.5, z as f32 - 0.5)
+        })
+        .collect();
+    let mut triangles = Vec::new();
+    for face in [
+        [0, 2, 3, 1],
+        [4, 5, 7, 6],
+        [0, 1, 5, 4],
+        [2, 6, 7, 3],
+        [0, 4, 6, 2],
+        [1, 3, 7, 5],
+    ] {
+        quad(&mut triangles, face);
+    }
+    (positions, triangles)
+}
+
+fn octahedron() -> Triangles {
+    let positions = vec![
+        Vec3::new(0.5, 0.0, 0.0),
+        Vec3::new(-0.5, 0.0, 0.0),
+        Vec3::new(0.0, 0.5, 0.0),
+        Vec3::new(0.0, -0.5, 0.0),
+        Vec3::new(0.0, 0.0, 0.5),
+        Vec3::new(0.0, 0.0, -0.5),
+    ];
+    // One face in each octant, turned around when an odd number of its axes are negative
+    let mut triangles = Vec::new();
+    for octant in 0..8 {
+        let [x, y, z] = [octant & 1, 2 + ((octant >> 1) & 1), 4 + ((octant >> 2) & 1)];
+        match (octant as u32).count_ones() % 2 {
+            0 => triangles.push([x, y, z]),
+            _ => triangles.push([x, z, y]),
+        }
+    }
+    (positions, triangles)
+}
+
+fn icosphere(subdivisions: u32) -> Triangles {
+    let golden = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut positions: Vec<Vec3> = [
+        (-1.0, golden, 0.0),
+        (1.0, golden, 0.0),
+        (-1.0, -golden, 0.0),
+        (1.0, -golden, 0.0),
+        (0.0, -1.0, golden),
+        (0.0, 1.0, golden),
+        (0.0, -1.0, -golden),
+        (0.0, 1.0, -golden),
+        (golden, 0.0, -1.0),
+        (golden, 0.0, 1.0),
+        (-golden, 0.0, -1.0),
+        (-golden, 0.0, 1.0),
+    ]
+    .into_iter()
+    .map(|(x, y, z)| Vec3::new(x, y, z).normalized() * 0.5)
+    .collect();
+    let mut triangles = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: usize, b: usize| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let position = positions[a].lerp(positions[b], 0.5).normalized() * 0.5;
+                positions.push(position);
+                positions.len() - 1
+            })
+        };
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let [ab, bc, ca] = [midpoint(a, b), midpoint(b, c), midpoint(c, a)];
+                [[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+            })
+            .collect();
+    }
+    (positions, triangles)
+}
+
+// A point on a circle around the y axis, `i` of `count` steps around and turning toward -z
+fn around(i: usize, count: usize, radius: f32, y: f32) -> Vec3 {
+    let (sin, cos) = (TAU * i as f32 / count as f32).sin_cos();
+    Vec3::new(radius * cos, y, -radius * sin)
+}
+
+fn uv_sphere(segments: usize, rings: usize) -> Triangles {
+    // The north pole, then each ring between the poles from the top down, then the south pole
+    let mut positions = vec![Vec3::new(0.0, 0.5, 0.0)];
+    for ring in 1..rings {
+        let (sin, cos) = (PI * ring as f32 / rings as f32).sin_cos();
+        positions.extend((0..segments).map(|i| around(i, segments, 0.5 * sin, 0.5 * cos)));
+    }
+    let south = positions.len();
+    positions.push(Vec3::new(0.0, -0.5, 0.0));
+
+    let vertex = |ring: usize, i: usize| 1 + (ring - 1) * segments + i % segments;
+    let mut triangles = Vec::new();
+    for i in 0..segments {
+        triangles.push([0, vertex(1, i), vertex(1, i + 1)]);
+        for ring in 1..rings - 1 {
+            quad(
+                &mut triangles,
+                [
+                    vertex(ring, i),
+                    vertex(ring + 1, i),
+                    vertex(ring + 1, i + 1),
+                    vertex(ring, i + 1),
+                ],
+            );
+        }
+        triangles.push([south, vertex(rings - 1, i + 1), vertex(rings - 1, i)]);
+    }
+    (positions, triangles)
+}
+
+fn cylinder(segments: usize) -> Triangles {
+    // The two cap centers, then the top and bottom rims
+    let mut positions = vec![Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, -0.5, 0.0)];
+    positions.extend((0..segments).map(|i| around(i, segments, 0.5, 0.5)));
+    positions.extend((0..segments).map(|i| around(i, segments, 0.5, -0.5)));
+
+    let top = |i: usize| 2 + i % segments;
+    let bottom = |i: usize| 2 + segments + i % segments;
+    let mut triangles = Vec::new();
+    for i in 0..segments {
+        triangles.push([0, top(i), top(i + 1)]);
+        quad(
+            &mut triangles,
+            [top(i), bottom(i), bottom(i + 1), top(i + 1)],
+        );
+        triangles.push([1, bottom(i + 1), bottom(i)]);
+    }
+    (positions, triangles)
+}
+
+fn cone(segments: usize) -> Triangles {
+    // The tip, the middle of the base, then the base's rim
+    let mut positions = vec![Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, -0.5, 0.0)];
+    positions.extend((0..segments).map(|i| around(i, segments, 0.5, -0.5)));
+
+    let rim = |i: usize| 2 + i % segments;
+    let mut triangles = Vec::new();
+    for i in 0..segments {
+        triangles.push([0, rim(i), rim(i + 1)]);
+        triangles.push([1, rim(i + 1), rim(i)]);
+    }
+    (positions, triangles)
+}
+
+fn torus(segments: usize, sides: usize, thickness: f32) -> Triangles {
+    // The ring is sized so the whole torus is a unit across
+    let ring_radius = 0.5 / (1.0 + thickness);
+    let tube_radius = ring_radius * thickness;
+    let mut positions = Vec::with_capacity(segments * sides);
+    for i in 0..segments {
+        let direction = around(i, segments, 1.0, 0.0);
+        for j in 0..sides {
+            let (sin, cos) = (TAU * j as f32 / sides as f32).sin_cos();
+            positions.push(
+                direction * (ring_radius + tube_radius * cos)
+                    + Vec3::new(0.0, tube_radius * sin, 0.0),
+            );
+        }
+    }
+
+    let vertex = |i: usize, j: usize| (i % segments) * sides + j % sides;
+    let mut triangles = Vec::new();
+    for i in 0..segments {
+        for j in 0..sides {
+            quad(
+                &mut triangles,
+                [
+                    vertex(i, j),
+                    vertex(i + 1, j),
+                    vertex(i + 1, j + 1),
+                    vertex(i, j + 1),
+                ],
+            );
+        }
+    }
+    (positions, triangles)
+}
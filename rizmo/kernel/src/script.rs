@@ -0,0 +1,355 @@
+//! A line-oriented text form of a command stack, easier to write by hand and to review as a diff
+//! than JSON.
+//!
+//! ```text
+//! // Pull out the first face and paint it red
+//! split 0.5
+//! face
+//! pull
+//! translate 0 0 -1
+//! color #ff0000
+//! ```
+//!
+//! Every line holds one command and its arguments, separated by whitespace. Blank lines and
+//! anything after `//` are ignored.
+
+use std::fmt;
+
+use crate::command::{Command, Pivot, Smoothing, Space, Subdivision, Target};
+use crate::math::{Color, Vec3};
+
+/// Where and why a script couldn't be parsed. Lines and columns count from 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Parses a whole script, stopping at the first line that's wrong.
+pub fn parse_script(source: &str) -> Result<Vec<Command>, ScriptError> {
+    let mut commands = Vec::new();
+    for (number, line) in source.lines().enumerate() {
+        let line = line.split("//").next().unwrap_or_default();
+        let mut words = Words::new(line, number + 1);
+        let Some((column, name)) = words.next() else {
+            continue;
+        };
+        let command = match name {
+            "push" => Command::PushSelection,
+            "pop" => Command::PopSelection,
+            "face" => Command::MoveFaceSelection,
+            "edge" => Command::MoveEdgeSelection,
+            "translate" => Command::Translate {
+                delta: Vec3::new(
+                    words.number(name)?,
+                    words.number(name)?,
+                    words.number(name)?,
+                ),
+                space: words.option("space", Space::from_name)?,
+            },
+            "scale" => Command::Scale {
+                factor: Vec3::new(
+                    words.number(name)?,
+                    words.number(name)?,
+                    words.number(name)?,
+                ),
+                pivot: words.option("pivot", Pivot::from_name)?,
+                target: words.option("target", Target::from_name)?,
+            },
+            "rotate" => Command::Rotate {
+                axis: Vec3::new(
+                    words.number(name)?,
+                    words.number(name)?,
+                    words.number(name)?,
+                ),
+                angle: words.number(name)?,
+                pivot: words.option("pivot", Pivot::from_name)?,
+                target: words.option("target", Target::from_name)?,
+            },
+            "split" => Command::Split(words.number(name)?),
+            "poke" => Command::Poke(Vec3::new(
+                words.number(name)?,
+                words.number(name)?,
+                words.number(name)?,
+            )),
+            "pull" => Command::Pull,
+            "extrude" => Command::Extrude(words.number(name)?),
+            "inset" => Command::Inset(words.number(name)?),
+            "collapse" => Command::EdgeCollapse(words.number(name)?),
+            "flip" => Command::FlipEdge,
+            "delete" => Command::DeleteFace,
+            "fill" => Command::FillHole,
+            // Subdivide worked on the whole mesh before it had a target
+            "subdivide" => Command::Subdivide {
+                levels: words.count(name)?,
+                scheme: words.option("scheme", Subdivision::from_name)?,
+                target: words.option_or("target", Target::from_name, Target::Region)?,
+            },
+            "smooth" => Command::Smooth {
+                iterations: words.count(name)?,
+                factor: words.number(name)?,
+                mode: words.option("mode", Smoothing::from_name)?,
+                target: words.option("target", Target::from_name)?,
+            },
+            "color" => Command::Color(words.color()?),
+            _ => return Err(words.error(column, format!("unknown command \"{name}\""))),
+        };
+        words.end()?;
+        commands.push(command);
+    }
+    Ok(commands)
+}
+
+/// Prints commands one per line, so that parsing the result gives back exactly the same commands.
+pub fn print_script(commands: &[Command]) -> String {
+    let mut script = String::new();
+    for command in commands {
+        script.push_str(&print_command(command));
+        script.push('\n');
+    }
+    script
+}
+
+fn print_command(command: &Command) -> String {
+    match command {
+        Command::PushSelection => "push".into(),
+        Command::PopSelection => "pop".into(),
+        Command::MoveFaceSelection => "face".into(),
+        Command::MoveEdgeSelection => "edge".into(),
+        Command::Translate { delta, space } => match space {
+            Space::World => format!("translate {} {} {}", delta.x, delta.y, delta.z),
+            _ => format!(
+                "translate {} {} {} {}",
+                delta.x,
+                delta.y,
+                delta.z,
+                space.name()
+            ),
+        },
+        Command::Scale {
+            factor,
+            pivot,
+            target,
+        } => format!(
+            "scale {} {} {} {} {}",
+            factor.x,
+            factor.y,
+            factor.z,
+            pivot.name(),
+            target.name()
+        ),
+        Command::Rotate {
+            axis,
+            angle,
+            pivot,
+            target,
+        } => format!(
+            "rotate {} {} {} {angle} {} {}",
+            axis.x,
+            axis.y,
+            axis.z,
+            pivot.name(),
+            target.name()
+        ),
+        Command::Split(amount) => format!("split {amount}"),
+        Command::Poke(weights) => format!("poke {} {} {}", weights.x, weights.y, weights.z),
+        Command::Pull => "pull".into(),
+        Command::Extrude(distance) => format!("extrude {distance}"),
+        Command::Inset(amount) => format!("inset {amount}"),
+        Command::EdgeCollapse(amount) => format!("collapse {amount}"),
+        Command::FlipEdge => "flip".into(),
+        Command::DeleteFace => "delete".into(),
+        Command::FillHole => "fill".into(),
+        Command::Subdivide {
+            levels,
+            scheme,
+            target,
+        } => format!("subdivide {levels} {} {}", scheme.name(), target.name()),
+        Command::Smooth {
+            iterations,
+            factor,
+            mode,
+            target,
+        } => format!(
+            "smooth {iterations} {factor} {} {}",
+            mode.name(),
+            target.name()
+        ),
+        Command::Color(color) => format!("color {}", print_color(*color)),
+    }
+}
+
+// Hex when that's exact, otherwise every channel in full
+fn print_color(color: Color) -> String {
+    let channels = [color.r, color.g, color.b, color.a];
+    let bytes = channels.map(|channel| (channel * 255.0).round());
+    let exact = channels.iter().zip(bytes).all(|(&channel, byte)| {
+        (0.0..=255.0).contains(&byte) && (byte / 255.0).to_bits() == channel.to_bits()
+    });
+    if !exact {
+        return format!("{} {} {} {}", color.r, color.g, color.b, color.a);
+    }
+    let hex: String = bytes[..3]
+        .iter()
+        .map(|&byte| format!("{:02x}", byte as u8))
+        .collect();
+    match color.a {
+        1.0 => format!("#{hex}"),
+        _ => format!("#{hex}{:02x}", bytes[3] as u8),
+    }
+}
+
+// The words on a line and the columns they start at
+struct Words<'a> {
+    words: Vec<(usize, &'a str)>,
+    next: usize,
+    line: usize,
+    end_column: usize,
+}
+
+impl<'a> Words<'a> {
+    fn new(line: &'a str, number: usize) -> Self {
+        let mut words = Vec::new();
+        let mut start = None;
+        let mut column = 0;
+        for (i, (byte, character)) in line.char_indices().enumerate() {
+            column = i + 1;
+            match (character.is_whitespace(), start) {
+                (false, None) => start = Some((column, byte)),
+                (true, Some((start_column, start_byte))) => {
+                    words.push((start_column, &line[start_byte..byte]));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some((start_column, start_byte)) = start {
+            words.push((start_column, &line[start_byte..]));
+        }
+        Words {
+            words,
+            next: 0,
+            line: number,
+            end_column: column + 1,
+        }
+    }
+
+    fn next(&mut self) -> Option<(usize, &'a str)> {
+        let word = self.words.get(self.next).copied();
+        self.next += 1;
+        word
+    }
+
+    fn error(&self, column: usize, message: String) -> ScriptError {
+        ScriptError {
+            line: self.line,
+            column,
+            message,
+        }
+    }
+
+    fn number(&mut self, command: &str) -> Result<f32, ScriptError> {
+        let (column, word) = self.next().ok_or_else(|| {
+            self.error(
+                self.end_column,
+                format!("{command} needs {}", arguments(command)),
+            )
+        })?;
+        word.parse()
+            .map_err(|_| self.error(column, format!("expected a number, found \"{word}\"")))
+    }
+
+    fn count(&mut self, command: &str) -> Result<u32, ScriptError> {
+        let (column, word) = self.next().ok_or_else(|| {
+            self.error(
+                self.end_column,
+                format!("{command} needs {}", arguments(command)),
+            )
+        })?;
+        word.parse()
+            .map_err(|_| self.error(column, format!("expected a whole number, found \"{word}\"")))
+    }
+
+    // A name from a fixed set that can be left out for the default
+    fn option<T: Default>(
+        &mut self,
+        kind: &str,
+        from_name: fn(&str) -> Option<T>,
+    ) -> Result<T, ScriptError> {
+        self.option_or(kind, from_name, T::default())
+    }
+
+    fn option_or<T>(
+        &mut self,
+        kind: &str,
+        from_name: fn(&str) -> Option<T>,
+        default: T,
+    ) -> Result<T, ScriptError> {
+        let Some((column, word)) = self.words.get(self.next).copied() else {
+            return Ok(default);
+        };
+        self.next += 1;
+        from_name(word).ok_or_else(|| self.error(column, format!("unknown {kind} \"{word}\"")))
+    }
+
+    fn color(&mut self) -> Result<Color, ScriptError> {
+        let Some((column, word)) = self.next() else {
+            return Err(self.error(
+                self.end_column,
+                "color needs #rrggbb, #rrggbbaa or 3 or 4 numbers".into(),
+            ));
+        };
+        let Some(hex) = word.strip_prefix('#') else {
+            self.next -= 1;
+            let r = self.number("color")?;
+            let g = self.number("color")?;
+            let b = self.number("color")?;
+            let a = match self.words.get(self.next) {
+                Some(_) => self.number("color")?,
+                None => 1.0,
+            };
+            return Ok(Color::from_rgba(r, g, b, a));
+        };
+
+        if !matches!(hex.len(), 6 | 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(self.error(column, format!("\"{word}\" isn't #rrggbb or #rrggbbaa")));
+        }
+        let channel = |i: usize| match hex.get(i * 2..i * 2 + 2) {
+            Some(byte) => u8::from_str_radix(byte, 16).unwrap() as f32 / 255.0,
+            None => 1.0,
+        };
+        Ok(Color::from_rgba(
+            channel(0),
+            channel(1),
+            channel(2),
+            channel(3),
+        ))
+    }
+
+    fn end(&mut self) -> Result<(), ScriptError> {
+        match self.next() {
+            Some((column, word)) => Err(self.error(column, format!("unexpected \"{word}\""))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn arguments(command: &str) -> &'static str {
+    match command {
+        "translate" | "scale" | "poke" => "3 numbers",
+        "rotate" => "an axis and an angle",
+        "color" => "3 or 4 numbers",
+        "subdivide" => "a number of levels",
+        "smooth" => "a number of iterations and a factor",
+        _ => "a number",
+    }
+}
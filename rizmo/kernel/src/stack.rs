@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+
+use crate::command::Command;
+use crate::document::Document;
+use crate::error::{CommandError, DocumentError, ReplayError};
+use crate::mesh::{Mesh, MetaIndexId};
+use crate::primitive::Primitive;
+use crate::script::{ScriptError, parse_script, print_script};
+
+pub type CommandId = i32;
+
+/// Where evaluation of a CommandStack stops.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FinishLine {
+    /// Every command is evaluated.
+    #[default]
+    End,
+    /// No command is evaluated.
+    Start,
+    /// Commands are evaluated up to and including this one.
+    After(CommandId),
+}
+
+// The commands that build a model, kept in the order they were authored.
+//
+// Ids are handed out once and never reused, so the editor can keep referring to a command while
+// others are inserted, moved or removed around it. Replaying always walks the commands front to
+// back, stopping at the finish line, starting from the base mesh if there is one and the
+// primitive if not.
+#[derive(Default, Clone, Debug)]
+pub struct CommandStack {
+    commands: Vec<(CommandId, Command)>,
+    next_id: CommandId,
+    finish_line: FinishLine,
+    validate: bool,
+    base: Option<Mesh>,
+    primitive: Primitive,
+    metadata: BTreeMap<String, String>,
+}
+
+impl CommandStack {
+    pub fn new() -> Self {
+        CommandStack::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.finish_line = FinishLine::End;
+    }
+
+    pub fn push(&mut self, command: Command) -> CommandId {
+        self.insert(self.commands.len(), command)
+    }
+
+    /// Inserts a command so that it ends up at `position`, clamped to the end of the stack.
+    pub fn insert(&mut self, position: usize, command: Command) -> CommandId {
+        let id = self.get_new_command_id();
+        self.commands
+            .insert(position.min(self.commands.len()), (id, command));
+        id
+    }
+
+    pub fn insert_before(&mut self, id: CommandId, command: Command) -> Option<CommandId> {
+        let position = self.position(id)?;
+        Some(self.insert(position, command))
+    }
+
+    pub fn insert_after(&mut self, id: CommandId, command: Command) -> Option<CommandId> {
+        let position = self.position(id)?;
+        Some(self.insert(position + 1, command))
+    }
+
+    /// Inserts a command right at the finish line and moves the finish line past it, the same way
+    /// typing at a cursor works.
+    pub fn insert_at_finish_line(&mut self, command: Command) -> CommandId {
+        let position = self.finish_line_position();
+        let id = self.insert(position, command);
+        if self.finish_line != FinishLine::End {
+            self.finish_line = FinishLine::After(id);
+        }
+        id
+    }
+
+    /// Moves a command so that it ends up at `position`, clamped to the end of the stack.
+    pub fn move_command(&mut self, id: CommandId, position: usize) -> Option<()> {
+        let old_position = self.position(id)?;
+        let entry = self.commands.remove(old_position);
+        self.commands
+            .insert(position.min(self.commands.len()), entry);
+        Some(())
+    }
+
+    /// Removes a command. If the finish line was after it, it moves up to the previous command.
+    pub fn remove(&mut self, id: CommandId) -> Option<Command> {
+        let position = self.position(id)?;
+        if self.finish_line == FinishLine::After(id) {
+            self.finish_line = match position {
+                0 => FinishLine::Start,
+                _ => FinishLine::After(self.commands[position - 1].0),
+            };
+        }
+        Some(self.commands.remove(position).1)
+    }
+
+    pub fn get(&self, id: CommandId) -> Option<&Command> {
+        self.commands
+            .iter()
+            .find(|(command_id, _)| *command_id == id)
+            .map(|(_, command)| command)
+    }
+
+    pub fn get_mut(&mut self, id: CommandId) -> Option<&mut Command> {
+        self.commands
+            .iter_mut()
+            .find(|(command_id, _)| *command_id == id)
+            .map(|(_, command)| command)
+    }
+
+    pub fn position(&self, id: CommandId) -> Option<usize> {
+        self.commands
+            .iter()
+            .position(|(command_id, _)| *command_id == id)
+    }
+
+    pub fn finish_line(&self) -> FinishLine {
+        self.finish_line
+    }
+
+    /// Fails without changing anything if the finish line is after a command that isn't in the
+    /// stack.
+    pub fn set_finish_line(&mut self, finish_line: FinishLine) -> Option<()> {
+        if let FinishLine::After(id) = finish_line {
+            self.position(id)?;
+        }
+        self.finish_line = finish_line;
+        Some(())
+    }
+
+    /// The number of commands in front of the finish line.
+    pub fn finish_line_position(&self) -> usize {
+        match self.finish_line {
+            FinishLine::End => self.commands.len(),
+            FinishLine::Start => 0,
+            FinishLine::After(id) => self.position(id).map_or(self.commands.len(), |p| p + 1),
+        }
+    }
+
+    /// The mesh replays start from, None for the primitive.
+    pub fn base(&self) -> Option<&Mesh> {
+        self.base.as_ref()
+    }
+
+    pub fn set_base(&mut self, base: Option<Mesh>) {
+        self.base = base;
+    }
+
+    /// The primitive replays start from when there's no base mesh.
+    pub fn primitive(&self) -> Primitive {
+        self.primitive
+    }
+
+    /// Starts replays from a primitive, dropping the base mesh if there is one.
+    pub fn set_primitive(&mut self, primitive: Primitive) {
+        self.primitive = primitive;
+        self.base = None;
+    }
+
+    /// When enabled, replays check the mesh after every command and fail on the first command
+    /// that leaves it invalid. Useful for tracking down commands that corrupt `connections`.
+    pub fn set_validation(&mut self, enabled: bool) {
+        self.validate = enabled;
+    }
+
+    pub fn validation_enabled(&self) -> bool {
+        self.validate
+    }
+
+    /// The commands and their ids in authoring order.
+    pub fn iter(&self) -> impl Iterator<Item = (CommandId, &Command)> {
+        self.commands.iter().map(|(id, command)| (*id, command))
+    }
+
+    /// The commands in front of the finish line, in authoring order.
+    pub fn evaluated(&self) -> impl Iterator<Item = (CommandId, &Command)> {
+        self.iter().take(self.finish_line_position())
+    }
+
+    /// Rebuilds `mesh` from scratch by running every command up to the finish line.
+    ///
+    /// Stops at the first command that fails, leaving the mesh as the commands before it built it.
+    pub fn replay(
+        &self,
+        mesh: &mut Mesh,
+        selections: &mut Vec<MetaIndexId>,
+    ) -> Result<(), ReplayError> {
+        match &self.base {
+            Some(base) => mesh.clone_from(base),
+            None => *mesh = self.primitive.to_mesh(),
+        }
+        selections.clear();
+        selections.push(mesh.track_index(0).expect("a starting mesh has faces"));
+
+        for (command_id, command) in self.evaluated() {
+            if !self.validate {
+                command
+                    .call(mesh, selections)
+                    .map_err(|error| ReplayError { command_id, error })?;
+                continue;
+            }
+            // Checked on a copy so the mesh is left as it was before the command that broke it
+            let mut scratch = mesh.clone();
+            let mut scratch_selections = selections.clone();
+            command
+                .call(&mut scratch, &mut scratch_selections)
+                .map_err(|error| ReplayError { command_id, error })?;
+            let report = scratch.validate();
+            if !report.is_valid() {
+                return Err(ReplayError {
+                    command_id,
+                    error: CommandError::InvalidTopology(report),
+                });
+            }
+            *mesh = scratch;
+            *selections = scratch_selections;
+        }
+        Ok(())
+    }
+
+    /// Notes saved along with the commands.
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.metadata
+    }
+
+    /// Everything needed to rebuild the model, commands in authoring order.
+    pub fn to_document(&self) -> Document {
+        Document {
+            base: self.base.clone(),
+            primitive: self.primitive,
+            commands: self
+                .commands
+                .iter()
+                .map(|(_, command)| command.clone())
+                .collect(),
+            metadata: self.metadata.clone(),
+            ..Document::default()
+        }
+    }
+
+    /// Inserts the document's commands at the finish line and returns their new ids. Its base mesh
+    /// and primitive replace the current ones, so a document without a base drops the current
+    /// base, and its metadata is merged in.
+    pub fn extend_from_document(&mut self, document: Document) -> Vec<CommandId> {
+        self.set_primitive(document.primitive);
+        self.set_base(document.base);
+        self.metadata.extend(document.metadata);
+        document
+            .commands
+            .into_iter()
+            .map(|command| self.insert_at_finish_line(command))
+            .collect()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        self.to_document().to_json()
+    }
+
+    /// Loads a document with Document::from_json and extends the stack with it. Nothing changes if
+    /// it doesn't load.
+    pub fn extend_from_json(&mut self, string: &str) -> Result<Vec<CommandId>, DocumentError> {
+        Ok(self.extend_from_document(Document::from_json(string)?))
+    }
+
+    /// Prints every command as a script. The base mesh, primitive and metadata aren't part of
+    /// scripts.
+    pub fn to_script(&self) -> String {
+        let commands: Vec<Command> = self
+            .commands
+            .iter()
+            .map(|(_, command)| command.clone())
+            .collect();
+        print_script(&commands)
+    }
+
+    /// Parses a script and inserts its commands at the finish line, returning their new ids.
+    /// Nothing changes if it doesn't parse.
+    pub fn extend_from_script(&mut self, script: &str) -> Result<Vec<CommandId>, ScriptError> {
+        Ok(parse_script(script)?
+            .into_iter()
+            .map(|command| self.insert_at_finish_line(command))
+            .collect())
+    }
+
+    fn get_new_command_id(&mut self) -> CommandId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+}
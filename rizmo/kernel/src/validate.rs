@@ -0,0 +1,224 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::mesh::{Mesh, decompose_meta_index};
+
+/// A single way the mesh breaks the rules the commands rely on.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TopologyIssue {
+    /// `indices` and `connections` have to be the same length and describe whole triangles.
+    MismatchedLengths { indices: usize, connections: usize },
+    /// The corner uses a vertex that isn't live.
+    VertexOutOfRange { meta_index: usize, vertex: i32 },
+    /// The corner uses a vertex that was deleted.
+    DeletedVertexInUse { meta_index: usize, vertex: i32 },
+    /// The half-edge's twin isn't a half-edge of the mesh or `Mesh::BOUNDARY`.
+    ConnectionOutOfRange { meta_index: usize, connection: i32 },
+    /// The half-edge's twin is in the same face.
+    TwinInSameFace { meta_index: usize, twin: usize },
+    /// `connections[connections[meta_index]] != meta_index`
+    AsymmetricTwin { meta_index: usize, twin: usize },
+    /// The twin runs between different vertices.
+    MismatchedEdge { meta_index: usize, twin: usize },
+    /// The twin runs between the same vertices in the same direction, so the faces on either
+    /// side of the edge disagree about which way is out.
+    InconsistentOrientation { meta_index: usize, twin: usize },
+    /// A live vertex that no face uses.
+    UnusedVertex { vertex: usize },
+    /// The faces around a vertex don't form a single fan, so moving it would tear the mesh.
+    NonManifoldVertex { vertex: usize },
+}
+
+impl fmt::Display for TopologyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TopologyIssue::MismatchedLengths {
+                indices,
+                connections,
+            } => write!(
+                f,
+                "{indices} indices and {connections} connections don't describe whole triangles"
+            ),
+            TopologyIssue::VertexOutOfRange { meta_index, vertex } => {
+                write!(
+                    f,
+                    "corner {meta_index} uses vertex {vertex} which isn't live"
+                )
+            }
+            TopologyIssue::DeletedVertexInUse { meta_index, vertex } => {
+                write!(f, "corner {meta_index} uses deleted vertex {vertex}")
+            }
+            TopologyIssue::ConnectionOutOfRange {
+                meta_index,
+                connection,
+            } => write!(
+                f,
+                "half-edge {meta_index} connects to {connection} which doesn't exist"
+            ),
+            TopologyIssue::TwinInSameFace { meta_index, twin } => {
+                write!(
+                    f,
+                    "half-edge {meta_index} connects to {twin} in the same face"
+                )
+            }
+            TopologyIssue::AsymmetricTwin { meta_index, twin } => {
+                write!(
+                    f,
+                    "half-edge {meta_index} connects to {twin} but not the other way"
+                )
+            }
+            TopologyIssue::MismatchedEdge { meta_index, twin } => {
+                write!(
+                    f,
+                    "half-edges {meta_index} and {twin} don't share endpoints"
+                )
+            }
+            TopologyIssue::InconsistentOrientation { meta_index, twin } => {
+                write!(
+                    f,
+                    "half-edges {meta_index} and {twin} run the same direction"
+                )
+            }
+            TopologyIssue::UnusedVertex { vertex } => write!(f, "vertex {vertex} is unused"),
+            TopologyIssue::NonManifoldVertex { vertex } => {
+                write!(
+                    f,
+                    "the faces around vertex {vertex} don't form a single fan"
+                )
+            }
+        }
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct ValidationReport {
+    pub issues: Vec<TopologyIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_valid() {
+            return write!(f, "the mesh is valid");
+        }
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{issue}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Mesh {
+    /// Checks the invariants that traversal and every command rely on.
+    ///
+    /// Later checks assume earlier ones passed, so once indices or connections are found to be out
+    /// of range the report stops there rather than indexing out of bounds.
+    pub fn validate(&self) -> ValidationReport {
+        let mut issues = Vec::new();
+
+        if self.indices.len() != self.connections.len() || !self.indices.len().is_multiple_of(3) {
+            issues.push(TopologyIssue::MismatchedLengths {
+                indices: self.indices.len(),
+                connections: self.connections.len(),
+            });
+            return ValidationReport { issues };
+        }
+
+        let deleted: HashSet<usize> = self.deleted_vertices().iter().copied().collect();
+        for (meta_index, &vertex) in self.indices.iter().enumerate() {
+            if vertex < 0 || vertex as usize >= self.vertex_count() {
+                issues.push(TopologyIssue::VertexOutOfRange { meta_index, vertex });
+            } else if deleted.contains(&(vertex as usize)) {
+                issues.push(TopologyIssue::DeletedVertexInUse { meta_index, vertex });
+            }
+        }
+        for (meta_index, &connection) in self.connections.iter().enumerate() {
+            if connection == Mesh::BOUNDARY {
+                continue;
+            }
+            if connection < 0 || connection as usize >= self.connections.len() {
+                issues.push(TopologyIssue::ConnectionOutOfRange {
+                    meta_index,
+                    connection,
+                });
+            }
+        }
+        if !issues.is_empty() {
+            return ValidationReport { issues };
+        }
+
+        for meta_index in 0..self.connections.len() {
+            let Some(twin) = self.twin(meta_index) else {
+                continue;
+            };
+            if decompose_meta_index(twin).0 == decompose_meta_index(meta_index).0 {
+                issues.push(TopologyIssue::TwinInSameFace { meta_index, twin });
+                continue;
+            }
+            if self.connections[twin] as usize != meta_index {
+                issues.push(TopologyIssue::AsymmetricTwin { meta_index, twin });
+                continue;
+            }
+            // Each edge only needs checking from one side
+            if twin < meta_index {
+                continue;
+            }
+            let (from, to) = self.edge(meta_index);
+            let (twin_from, twin_to) = self.edge(twin);
+            if (from, to) == (twin_from, twin_to) {
+                issues.push(TopologyIssue::InconsistentOrientation { meta_index, twin });
+            } else if (from, to) != (twin_to, twin_from) {
+                issues.push(TopologyIssue::MismatchedEdge { meta_index, twin });
+            }
+        }
+        if !issues.is_empty() {
+            return ValidationReport { issues };
+        }
+
+        // With the twins sound, walking from a corner to the next corner around the same vertex
+        // always cycles or runs into a boundary both ways. Every corner using a vertex has to be
+        // on one fan.
+        let mut corners_per_vertex = vec![0; self.vertex_count()];
+        for &vertex in self.indices.iter() {
+            corners_per_vertex[vertex as usize] += 1;
+        }
+        let mut visited = vec![false; self.indices.len()];
+        let mut non_manifold = HashSet::new();
+        for meta_index in 0..self.indices.len() {
+            if visited[meta_index] {
+                continue;
+            }
+            let vertex = self.indices[meta_index] as usize;
+            let fan = self.vertex_fan(meta_index).unwrap_or_default();
+            for &corner in fan.iter() {
+                visited[corner] = true;
+            }
+            if fan.len() != corners_per_vertex[vertex] && non_manifold.insert(vertex) {
+                issues.push(TopologyIssue::NonManifoldVertex { vertex });
+            }
+        }
+        for (vertex, &corners) in corners_per_vertex.iter().enumerate() {
+            if corners == 0 && !deleted.contains(&vertex) {
+                issues.push(TopologyIssue::UnusedVertex { vertex });
+            }
+        }
+
+        ValidationReport { issues }
+    }
+
+    fn edge(&self, meta_index: usize) -> (i32, i32) {
+        let (start, offset) = decompose_meta_index(meta_index);
+        (
+            self.indices[meta_index],
+            self.indices[start + (offset + 1) % 3],
+        )
+    }
+}
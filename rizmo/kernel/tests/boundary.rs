@@ -0,0 +1,191 @@
+use rizmo_kernel::{
+    Color, Command, CommandError, Mesh, MetaIndexId, Smoothing, Space, Subdivision, Target, Vec3,
+};
+
+fn run(commands: &[Command]) -> (Mesh, Vec<MetaIndexId>) {
+    let mut mesh = Mesh::new();
+    let mut selections = vec![mesh.track_index(0).unwrap()];
+    for command in commands {
+        command.call(&mut mesh, &mut selections).unwrap();
+    }
+    (mesh, selections)
+}
+
+fn boundary_edges(mesh: &Mesh) -> usize {
+    mesh.connections
+        .iter()
+        .filter(|&&connection| connection == Mesh::BOUNDARY)
+        .count()
+}
+
+#[test]
+fn deleting_a_face_leaves_a_hole() {
+    let (mesh, selections) = run(&[Command::DeleteFace]);
+
+    assert_eq!(mesh.face_count(), 3);
+    assert_eq!(boundary_edges(&mesh), 3);
+    assert!(mesh.validate().is_valid());
+    // The selection crossed to the face on the other side of its edge
+    let selection = mesh.get_meta_index(selections[0]).unwrap() as usize;
+    assert_eq!(mesh.connections[selection], Mesh::BOUNDARY);
+    assert_eq!(mesh.boundary_loop(selection).unwrap().len(), 3);
+}
+
+#[test]
+fn selections_stop_at_boundaries() {
+    let mut mesh = Mesh::new();
+    let mut selections = vec![mesh.track_index(0).unwrap()];
+    Command::DeleteFace
+        .call(&mut mesh, &mut selections)
+        .unwrap();
+
+    assert_eq!(
+        Command::MoveFaceSelection.call(&mut mesh, &mut selections),
+        Err(CommandError::BoundaryEdge(8))
+    );
+    assert_eq!(
+        Command::FlipEdge.call(&mut mesh, &mut selections),
+        Err(CommandError::BoundaryEdge(8))
+    );
+}
+
+#[test]
+fn boundary_vertices_can_still_be_moved() {
+    let (mesh, _) = run(&[
+        Command::DeleteFace,
+        Command::Translate {
+            delta: Vec3::new(-1.0, 0.0, 0.0),
+            space: Space::World,
+        },
+    ]);
+
+    // Half-edge 8 starts at vertex 2, which all three remaining faces used to share
+    assert_eq!(mesh.positions[2], Vec3::new(-1.0, 1.0, 0.0));
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn split_on_a_boundary_only_splits_one_face() {
+    let (mesh, selections) = run(&[Command::DeleteFace, Command::Split(0.5)]);
+
+    assert_eq!(mesh.face_count(), 4);
+    assert_eq!(boundary_edges(&mesh), 4);
+    assert!(mesh.validate().is_valid());
+    let selection = mesh.get_meta_index(selections[0]).unwrap() as usize;
+    assert_eq!(mesh.boundary_loop(selection).unwrap().len(), 4);
+}
+
+#[test]
+fn fill_hole_closes_a_triangle() {
+    let (mesh, _) = run(&[Command::DeleteFace, Command::FillHole]);
+
+    assert_eq!(mesh.face_count(), 4);
+    assert_eq!(boundary_edges(&mesh), 0);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn fill_hole_fans_around_a_new_vertex() {
+    let (mesh, _) = run(&[
+        Command::Color(Color::WHITE),
+        Command::DeleteFace,
+        Command::DeleteFace,
+        Command::FillHole,
+    ]);
+
+    assert_eq!(mesh.face_count(), 6);
+    assert_eq!(mesh.vertex_count(), 5);
+    assert_eq!(boundary_edges(&mesh), 0);
+    assert!(mesh.validate().is_valid());
+    // The four corners of the hole are the four corners of the tetrahedron
+    assert_eq!(mesh.positions[4], Vec3::new(0.25, 0.25, 0.25));
+    assert_eq!(mesh.colors[4].r, 0.25);
+}
+
+#[test]
+fn subdividing_keeps_holes_in_their_outline() {
+    let (mesh, _) = run(&[
+        Command::DeleteFace,
+        Command::Subdivide {
+            levels: 1,
+            scheme: Subdivision::Loop,
+            target: Target::Region,
+        },
+    ]);
+
+    assert_eq!(mesh.face_count(), 12);
+    assert_eq!(boundary_edges(&mesh), 6);
+    assert!(mesh.validate().is_valid());
+    // The deleted face was in the x = 0 plane, and the hole's edge stays there
+    for (meta_index, &connection) in mesh.connections.iter().enumerate() {
+        if connection == Mesh::BOUNDARY {
+            assert_eq!(mesh.positions[mesh.indices[meta_index] as usize].x, 0.0);
+        }
+    }
+    assert_eq!(mesh.positions[0], Vec3::new(0.0, 0.125, 0.125));
+}
+
+#[test]
+fn smoothing_keeps_holes_in_their_outline() {
+    let (mesh, _) = run(&[
+        Command::DeleteFace,
+        Command::Smooth {
+            iterations: 2,
+            factor: 0.5,
+            mode: Smoothing::Laplacian,
+            target: Target::Region,
+        },
+    ]);
+
+    for vertex in 0..3 {
+        assert_eq!(mesh.positions[vertex].x, 0.0);
+    }
+    assert_ne!(mesh.positions[3], Vec3::new(1.0, 0.0, 0.0));
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn poke_keeps_the_boundary_edge() {
+    let (mesh, selections) = run(&[Command::DeleteFace, Command::Poke(Vec3::new(1.0, 1.0, 1.0))]);
+
+    assert_eq!(mesh.face_count(), 5);
+    assert_eq!(boundary_edges(&mesh), 3);
+    assert!(mesh.validate().is_valid());
+    let selection = mesh.get_meta_index(selections[0]).unwrap() as usize;
+    assert_eq!(mesh.connections[selection], Mesh::BOUNDARY);
+}
+
+#[test]
+fn the_last_face_cannot_be_deleted() {
+    let (mut mesh, mut selections) = run(&[
+        Command::DeleteFace,
+        Command::DeleteFace,
+        Command::DeleteFace,
+    ]);
+
+    assert_eq!(mesh.face_count(), 1);
+    assert_eq!(mesh.deleted_vertices().len(), 1);
+    assert!(mesh.validate().is_valid());
+    assert_eq!(
+        Command::DeleteFace.call(&mut mesh, &mut selections),
+        Err(CommandError::DeleteLastFace)
+    );
+    assert_eq!(Command::FillHole.call(&mut mesh, &mut selections), Ok(()));
+    assert_eq!(mesh.face_count(), 2);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn subdividing_next_to_a_hole_keeps_it_open() {
+    let (mesh, _) = run(&[
+        Command::DeleteFace,
+        Command::Subdivide {
+            levels: 2,
+            scheme: Subdivision::Loop,
+            target: Target::Face,
+        },
+    ]);
+
+    assert!(boundary_edges(&mesh) > 3);
+    assert!(mesh.validate().is_valid());
+}
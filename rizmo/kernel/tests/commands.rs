@@ -0,0 +1,783 @@
+use rizmo_kernel::{
+    Color, Command, CommandError, Mesh, MetaIndexId, Pivot, Smoothing, Space, Subdivision, Target,
+    Vec3, decompose_meta_index, parse_script,
+};
+
+fn tetrahedron() -> (Mesh, Vec<MetaIndexId>) {
+    let mut mesh = Mesh::new();
+    let selections = vec![mesh.track_index(0).unwrap()];
+    (mesh, selections)
+}
+
+fn run(commands: &[Command]) -> (Mesh, Vec<MetaIndexId>) {
+    let (mut mesh, mut selections) = tetrahedron();
+    for command in commands {
+        command.call(&mut mesh, &mut selections).unwrap();
+    }
+    (mesh, selections)
+}
+
+fn edge_positions(mesh: &Mesh, meta_index: usize) -> (Vec3, Vec3) {
+    let (start, offset) = decompose_meta_index(meta_index);
+    let next = start + (offset + 1) % 3;
+    (
+        mesh.positions[mesh.indices[meta_index] as usize],
+        mesh.positions[mesh.indices[next] as usize],
+    )
+}
+
+fn assert_near(a: Vec3, b: Vec3) {
+    assert!(a.distance_to(b) < 1e-5, "{a:?} isn't near {b:?}");
+}
+
+fn assert_connections_consistent(mesh: &Mesh) {
+    assert_eq!(mesh.indices.len(), mesh.connections.len());
+    for (meta_index, &twin) in mesh.connections.iter().enumerate() {
+        assert_eq!(mesh.connections[twin as usize], meta_index as i32);
+        let (from, to) = edge_positions(mesh, meta_index);
+        let (twin_from, twin_to) = edge_positions(mesh, twin as usize);
+        assert_eq!((from, to), (twin_to, twin_from), "half-edge {meta_index}");
+    }
+}
+
+#[test]
+fn tetrahedron_is_consistent() {
+    let (mesh, _) = tetrahedron();
+    assert_eq!(mesh.vertex_count(), 4);
+    assert_eq!(mesh.face_count(), 4);
+    assert_connections_consistent(&mesh);
+}
+
+#[test]
+fn split_inserts_a_vertex_along_the_edge() {
+    let (mesh, _) = run(&[Command::Split(0.25)]);
+
+    assert_eq!(mesh.vertex_count(), 5);
+    assert_eq!(mesh.face_count(), 6);
+    // Meta index 0 runs from (0, 0, 0) to (0, 1, 0)
+    assert_eq!(mesh.positions[4], Vec3::new(0.0, 0.25, 0.0));
+    assert_eq!(mesh.indices[1], 4);
+    assert_connections_consistent(&mesh);
+}
+
+#[test]
+fn split_twice_stays_consistent() {
+    let (mesh, _) = run(&[
+        Command::Split(0.5),
+        Command::MoveFaceSelection,
+        Command::MoveEdgeSelection,
+        Command::Split(0.5),
+    ]);
+
+    assert_eq!(mesh.vertex_count(), 6);
+    assert_eq!(mesh.face_count(), 8);
+    assert_connections_consistent(&mesh);
+}
+
+#[test]
+fn pull_detaches_the_selected_corner() {
+    let (mesh, _) = run(&[Command::Pull]);
+
+    assert_eq!(mesh.vertex_count(), 5);
+    assert_eq!(mesh.face_count(), 6);
+    assert_eq!(mesh.indices[0], 4);
+    assert_eq!(mesh.positions[4], mesh.positions[0]);
+    assert_connections_consistent(&mesh);
+}
+
+#[test]
+fn extrude_raises_a_cap_on_the_selected_face() {
+    let (mesh, selections) = run(&[Command::Extrude(2.0)]);
+
+    assert_eq!(mesh.vertex_count(), 7);
+    assert_eq!(mesh.face_count(), 10);
+    // Face 0 is (0, 0, 0), (0, 1, 0), (0, 0, 1) and faces -x
+    assert_eq!(&mesh.indices[0..3], &[4, 5, 6]);
+    assert_eq!(mesh.positions[4], Vec3::new(-2.0, 0.0, 0.0));
+    assert_eq!(mesh.positions[5], Vec3::new(-2.0, 1.0, 0.0));
+    assert_eq!(mesh.get_meta_index(selections[0]), Ok(0));
+    assert_eq!(mesh.face_normal(0), Vec3::new(-1.0, 0.0, 0.0));
+    assert_connections_consistent(&mesh);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn extruded_caps_can_be_extruded_again() {
+    let (mesh, _) = run(&[
+        Command::Extrude(1.0),
+        Command::Extrude(0.5),
+        Command::MoveFaceSelection,
+        Command::Extrude(0.5),
+        Command::Split(0.5),
+    ]);
+
+    assert_eq!(mesh.face_count(), 4 + 3 * 6 + 2);
+    assert_connections_consistent(&mesh);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn extrude_rejects_distances_that_arent_finite() {
+    let (mut mesh, mut selections) = tetrahedron();
+
+    assert_eq!(
+        Command::Extrude(f32::INFINITY).call(&mut mesh, &mut selections),
+        Err(CommandError::ExtrudeDistanceNotFinite(f32::INFINITY))
+    );
+    let extrude = parse_script("extrude NaN").unwrap().remove(0);
+    assert!(matches!(
+        extrude.call(&mut mesh, &mut selections),
+        Err(CommandError::ExtrudeDistanceNotFinite(distance)) if distance.is_nan()
+    ));
+    assert_eq!(mesh.face_count(), 4);
+}
+
+#[test]
+fn inset_shrinks_the_face_toward_its_centroid() {
+    let (mesh, selections) = run(&[Command::Inset(0.5)]);
+
+    assert_eq!(mesh.vertex_count(), 7);
+    assert_eq!(mesh.face_count(), 10);
+    assert_eq!(mesh.get_meta_index(selections[0]), Ok(0));
+    // Halfway between (0, 0, 0) and the centroid of face 0
+    let centroid = Vec3::new(0.0, 1.0, 1.0) / 3.0;
+    assert_eq!(mesh.positions[mesh.indices[0] as usize], centroid / 2.0);
+    assert_eq!(mesh.face_normal(0), Vec3::new(-1.0, 0.0, 0.0));
+    assert_connections_consistent(&mesh);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn inset_rejects_amounts_past_the_centroid() {
+    let (mut mesh, mut selections) = tetrahedron();
+
+    assert_eq!(
+        Command::Inset(-0.1).call(&mut mesh, &mut selections),
+        Err(CommandError::InsetAmountOutOfRange(-0.1))
+    );
+    // The whole way would leave the inner face with no area
+    assert_eq!(
+        Command::Inset(1.0).call(&mut mesh, &mut selections),
+        Err(CommandError::InsetAmountOutOfRange(1.0))
+    );
+    assert_eq!(mesh.face_count(), 4);
+}
+
+#[test]
+fn edge_collapse_undoes_a_split() {
+    // The split vertex is the end of the selected half-edge, so it's the one removed
+    let (mesh, selections) = run(&[Command::Split(0.5), Command::EdgeCollapse(0.0)]);
+
+    assert_eq!(mesh.face_count(), 4);
+    assert_eq!(mesh.deleted_vertices(), &[4]);
+    assert!(!mesh.indices.contains(&4));
+    assert_eq!(mesh.positions[0], Vec3::ZERO);
+    let selection = mesh.get_meta_index(selections[0]).unwrap() as usize;
+    assert_eq!(mesh.indices[selection], 0);
+    assert_connections_consistent(&mesh);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn edge_collapse_moves_the_merged_vertex_along_the_edge() {
+    let (mesh, selections) = run(&[
+        Command::Extrude(1.0),
+        Command::MoveFaceSelection,
+        Command::EdgeCollapse(0.25),
+    ]);
+
+    assert_eq!(mesh.face_count(), 8);
+    assert_eq!(mesh.deleted_vertices().len(), 1);
+    let selection = mesh.get_meta_index(selections[0]).unwrap() as usize;
+    // The side face's edge ran from (-1, 1, 0) to (-1, 0, 0)
+    assert_eq!(
+        mesh.positions[mesh.indices[selection] as usize],
+        Vec3::new(-1.0, 0.75, 0.0)
+    );
+    assert_connections_consistent(&mesh);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn edge_collapse_moves_tied_vertices_too() {
+    // After the pull the selected edge runs from the pulled vertex to the split vertex
+    let (mut mesh, mut selections) = run(&[Command::Split(0.5), Command::Pull]);
+    let far_end = mesh.indices[1] as usize;
+    let seam_corner = mesh.vertex_fan(1).unwrap()[1];
+    let tied = mesh.add_vertex(mesh.positions[far_end]);
+    mesh.colors[tied] = Color::from_rgb(1.0, 0.0, 0.0);
+    mesh.indices[seam_corner] = tied as i32;
+
+    Command::EdgeCollapse(0.0)
+        .call(&mut mesh, &mut selections)
+        .unwrap();
+
+    let merged = mesh.positions[mesh.indices[0] as usize];
+    assert_eq!(merged, Vec3::ZERO);
+    assert_eq!(mesh.positions[tied], merged);
+    assert_eq!(mesh.indices[seam_corner], tied as i32);
+    assert_eq!(mesh.deleted_vertices(), &[far_end]);
+    assert_connections_consistent(&mesh);
+}
+
+#[test]
+fn edge_collapse_refuses_to_flatten_a_tetrahedron() {
+    let (mut mesh, mut selections) = tetrahedron();
+
+    assert_eq!(
+        Command::EdgeCollapse(0.5).call(&mut mesh, &mut selections),
+        Err(CommandError::CollapseBreaksManifold(0))
+    );
+    assert_eq!(mesh.face_count(), 4);
+    assert!(mesh.deleted_vertices().is_empty());
+}
+
+#[test]
+fn flip_edge_turns_the_diagonal_of_a_side_quad() {
+    // Half-edge 15 is the diagonal of the first side quad, running from vertex 0 to vertex 5
+    let path = [
+        Command::Extrude(1.0),
+        Command::MoveFaceSelection,
+        Command::MoveEdgeSelection,
+        Command::MoveEdgeSelection,
+    ];
+    let (before, _) = run(&path);
+    let (mesh, selections) = run(&[&path[..], &[Command::FlipEdge]].concat());
+
+    let selection = mesh.get_meta_index(selections[0]).unwrap() as usize;
+    assert_eq!(selection, 15);
+    assert_eq!(
+        edge_positions(&mesh, selection),
+        (mesh.positions[4], mesh.positions[2])
+    );
+    assert_eq!(mesh.positions, before.positions);
+    assert_eq!(mesh.face_count(), before.face_count());
+    assert_connections_consistent(&mesh);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn flip_edge_refuses_to_duplicate_or_flatten() {
+    let (mut mesh, mut selections) = tetrahedron();
+    assert_eq!(
+        Command::FlipEdge.call(&mut mesh, &mut selections),
+        Err(CommandError::FlipDuplicatesEdge(0))
+    );
+
+    // Both faces on half-edge 1 lie in the same plane with the split vertex between them
+    let (mut mesh, mut selections) = run(&[Command::Split(0.5), Command::MoveEdgeSelection]);
+    let before = mesh.indices.clone();
+    assert_eq!(
+        Command::FlipEdge.call(&mut mesh, &mut selections),
+        Err(CommandError::FlipMakesDegenerateFace(1))
+    );
+    assert_eq!(mesh.indices, before);
+}
+
+#[test]
+fn face_space_translate_follows_the_face() {
+    let outward = Command::Translate {
+        delta: Vec3::new(0.0, 0.0, 1.0),
+        space: Space::Face,
+    };
+    let (mesh, _) = run(std::slice::from_ref(&outward));
+    assert_near(mesh.positions[0], Vec3::new(-1.0, 0.0, 0.0));
+
+    let (mesh, _) = run(&[
+        Command::Rotate {
+            axis: Vec3::new(0.0, 0.0, 1.0),
+            angle: 90.0,
+            pivot: Pivot::Origin,
+            target: Target::Region,
+        },
+        outward,
+    ]);
+    assert_near(mesh.positions[0], Vec3::new(0.0, -1.0, 0.0));
+}
+
+#[test]
+fn face_space_axes_follow_the_selected_edge() {
+    // Face 0 starts at vertex 0 and runs toward vertex 2 at (0, 1, 0)
+    let (mesh, _) = run(&[Command::Translate {
+        delta: Vec3::new(1.0, 2.0, 0.0),
+        space: Space::Face,
+    }]);
+    assert_near(mesh.positions[0], Vec3::new(0.0, 1.0, -2.0));
+}
+
+#[test]
+fn scale_moves_the_face_away_from_its_centroid() {
+    let (mesh, _) = run(&[Command::Scale {
+        factor: Vec3::new(1.0, 2.0, 2.0),
+        pivot: Pivot::FaceCentroid,
+        target: Target::Face,
+    }]);
+
+    let third = 1.0 / 3.0;
+    assert_near(mesh.positions[0], Vec3::new(0.0, -third, -third));
+    assert_near(mesh.positions[2], Vec3::new(0.0, 1.0 + 2.0 * third, -third));
+    assert_eq!(mesh.positions[3], Vec3::new(1.0, 0.0, 0.0));
+}
+
+#[test]
+fn scale_about_the_bounds_moves_the_whole_region() {
+    let (mesh, _) = run(&[Command::Scale {
+        factor: Vec3::new(2.0, 2.0, 2.0),
+        pivot: Pivot::BoundsCenter,
+        target: Target::Region,
+    }]);
+
+    assert_near(mesh.positions[0], Vec3::new(-0.5, -0.5, -0.5));
+    assert_near(mesh.positions[3], Vec3::new(1.5, -0.5, -0.5));
+}
+
+#[test]
+fn scale_moves_tied_vertices_together() {
+    let (mut mesh, mut selections) = tetrahedron();
+    // Give a corner of the next face its own copy of vertex 1, the way a seam in the colors would
+    let tied = mesh.add_vertex(mesh.positions[1]);
+    mesh.indices[4] = tied as i32;
+
+    Command::Scale {
+        factor: Vec3::new(2.0, 2.0, 2.0),
+        pivot: Pivot::Origin,
+        target: Target::Face,
+    }
+    .call(&mut mesh, &mut selections)
+    .unwrap();
+
+    assert_eq!(mesh.positions[1], Vec3::new(0.0, 0.0, 2.0));
+    assert_eq!(mesh.positions[tied], mesh.positions[1]);
+    assert_eq!(mesh.positions[3], Vec3::new(1.0, 0.0, 0.0));
+}
+
+#[test]
+fn rotate_turns_around_the_axis() {
+    let (mesh, _) = run(&[Command::Rotate {
+        axis: Vec3::new(0.0, 0.0, 2.0),
+        angle: 90.0,
+        pivot: Pivot::Origin,
+        target: Target::Region,
+    }]);
+
+    assert_near(mesh.positions[3], Vec3::new(0.0, 1.0, 0.0));
+    assert_near(mesh.positions[2], Vec3::new(-1.0, 0.0, 0.0));
+    assert_near(mesh.positions[1], Vec3::new(0.0, 0.0, 1.0));
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn rotate_about_the_selected_vertex_leaves_it_in_place() {
+    let (mesh, _) = run(&[
+        Command::MoveEdgeSelection,
+        Command::Rotate {
+            axis: Vec3::new(1.0, 0.0, 0.0),
+            angle: 180.0,
+            pivot: Pivot::Vertex,
+            target: Target::Face,
+        },
+    ]);
+
+    // The selection is on vertex 2 at (0, 1, 0)
+    assert_eq!(mesh.positions[2], Vec3::new(0.0, 1.0, 0.0));
+    assert_near(mesh.positions[0], Vec3::new(0.0, 2.0, 0.0));
+    assert_near(mesh.positions[1], Vec3::new(0.0, 2.0, -1.0));
+}
+
+#[test]
+fn rotate_needs_an_axis() {
+    let (mut mesh, mut selections) = tetrahedron();
+    let rotate = Command::Rotate {
+        axis: Vec3::ZERO,
+        angle: 45.0,
+        pivot: Pivot::default(),
+        target: Target::default(),
+    };
+
+    assert_eq!(
+        rotate.call(&mut mesh, &mut selections),
+        Err(CommandError::ZeroRotationAxis)
+    );
+}
+
+#[test]
+fn scale_and_rotate_reject_values_that_arent_finite() {
+    let (mut mesh, mut selections) = tetrahedron();
+    let positions = mesh.positions.clone();
+
+    let factor = Vec3::new(1.0, f32::INFINITY, 1.0);
+    let scale = Command::Scale {
+        factor,
+        pivot: Pivot::default(),
+        target: Target::default(),
+    };
+    assert_eq!(
+        scale.call(&mut mesh, &mut selections),
+        Err(CommandError::ScaleFactorNotFinite(factor))
+    );
+    let axis = Vec3::new(f32::INFINITY, 0.0, 0.0);
+    let rotate = Command::Rotate {
+        axis,
+        angle: 45.0,
+        pivot: Pivot::default(),
+        target: Target::default(),
+    };
+    assert_eq!(
+        rotate.call(&mut mesh, &mut selections),
+        Err(CommandError::RotationNotFinite(axis, 45.0))
+    );
+    let rotate = Command::Rotate {
+        axis: Vec3::new(0.0, 1.0, 0.0),
+        angle: f32::NAN,
+        pivot: Pivot::default(),
+        target: Target::default(),
+    };
+    assert!(matches!(
+        rotate.call(&mut mesh, &mut selections),
+        Err(CommandError::RotationNotFinite(_, angle)) if angle.is_nan()
+    ));
+    assert_eq!(mesh.positions, positions);
+}
+
+#[test]
+fn subdivide_splits_every_face_into_four() {
+    let (mesh, _) = run(&[Command::Subdivide {
+        levels: 2,
+        scheme: Subdivision::Loop,
+        target: Target::Region,
+    }]);
+
+    assert_eq!(mesh.face_count(), 64);
+    assert_eq!(mesh.vertex_count(), 34);
+    assert_connections_consistent(&mesh);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn loop_subdivision_smooths_the_old_vertices() {
+    let (mesh, _) = run(&[Command::Subdivide {
+        levels: 1,
+        scheme: Subdivision::Loop,
+        target: Target::Region,
+    }]);
+
+    // Three neighbours weigh 3/16 each
+    let weight = 3.0 / 16.0;
+    assert_near(mesh.positions[0], Vec3::new(weight, weight, weight));
+    // The first edge of face 0, from (0, 0, 0) to (0, 1, 0), with (0, 0, 1) and (1, 0, 0) across
+    assert_near(mesh.positions[4], Vec3::new(0.125, 0.375, 0.125));
+}
+
+#[test]
+fn midpoint_subdivision_keeps_the_shape() {
+    let (mesh, selections) = run(&[
+        Command::Color(Color::from_rgb(1.0, 0.0, 0.0)),
+        Command::MoveEdgeSelection,
+        Command::Subdivide {
+            levels: 1,
+            scheme: Subdivision::Midpoint,
+            target: Target::Region,
+        },
+    ]);
+
+    assert_eq!(mesh.positions[0], Vec3::new(0.0, 0.0, 0.0));
+    assert_eq!(mesh.positions[4], Vec3::new(0.0, 0.5, 0.0));
+    assert_eq!(
+        mesh.colors[4],
+        Color::from_rgb(1.0, 0.0, 0.0).lerp(Color::default(), 0.5)
+    );
+    // The selection was on vertex 2 pointing at vertex 1, and now points at the middle of that edge
+    assert_eq!(
+        mesh.get_face_positions(selections[0]).unwrap()[..2],
+        [Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.5, 0.5)]
+    );
+}
+
+#[test]
+fn subdivide_limits_the_levels() {
+    let (mut mesh, mut selections) = tetrahedron();
+    let subdivide = Command::Subdivide {
+        levels: 7,
+        scheme: Subdivision::Loop,
+        target: Target::Region,
+    };
+
+    assert_eq!(
+        subdivide.call(&mut mesh, &mut selections),
+        Err(CommandError::SubdivideLevelsOutOfRange(7))
+    );
+    assert_eq!(mesh.face_count(), 4);
+}
+
+#[test]
+fn subdivide_splits_only_the_target() {
+    let (mesh, selections) = run(&[Command::Subdivide {
+        levels: 1,
+        scheme: Subdivision::Loop,
+        target: Target::Face,
+    }]);
+
+    // The face splits into four and each neighbour into two along the shared edge
+    assert_eq!(mesh.face_count(), 4 + 3 * 2);
+    assert_eq!(mesh.vertex_count(), 7);
+    // Every old vertex is on a face that isn't split, so none of them move
+    assert_eq!(mesh.positions[..4], Mesh::new().positions[..4]);
+    assert_eq!(mesh.positions[4], Vec3::new(0.0, 0.5, 0.0));
+    assert_eq!(mesh.get_meta_index(selections[0]), Ok(0));
+    assert_connections_consistent(&mesh);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn subdivide_around_a_vertex_stays_joined_up() {
+    // One level splits every face of a tetrahedron, since they all touch each other
+    for levels in 2..=3 {
+        let (mesh, _) = run(&[
+            Command::MoveEdgeSelection,
+            Command::Subdivide {
+                levels,
+                scheme: Subdivision::Loop,
+                target: Target::Vertex,
+            },
+        ]);
+
+        assert!(mesh.face_count() < 4 * 4usize.pow(levels));
+        assert_connections_consistent(&mesh);
+        assert!(mesh.validate().is_valid(), "{levels} levels");
+    }
+}
+
+fn smooth(iterations: u32, factor: f32, mode: Smoothing, target: Target) -> Command {
+    Command::Smooth {
+        iterations,
+        factor,
+        mode,
+        target,
+    }
+}
+
+// How far the vertices are from their middle on average
+fn spread(mesh: &Mesh) -> f32 {
+    let positions = &mesh.positions[..mesh.vertex_count()];
+    let mut middle = Vec3::ZERO;
+    for &position in positions {
+        middle += position;
+    }
+    middle = middle / positions.len() as f32;
+    let total: f32 = positions.iter().map(|p| p.distance_to(middle)).sum();
+    total / positions.len() as f32
+}
+
+#[test]
+fn smooth_moves_vertices_toward_their_neighbours() {
+    let (mesh, _) = run(&[smooth(1, 1.0, Smoothing::Laplacian, Target::Region)]);
+
+    let third = 1.0 / 3.0;
+    assert_near(mesh.positions[0], Vec3::new(third, third, third));
+    assert_near(mesh.positions[3], Vec3::new(0.0, third, third));
+}
+
+#[test]
+fn smooth_only_moves_the_target() {
+    let (mesh, _) = run(&[smooth(3, 0.5, Smoothing::Laplacian, Target::Face)]);
+
+    assert_eq!(mesh.positions[3], Vec3::new(1.0, 0.0, 0.0));
+    assert_ne!(mesh.positions[0], Vec3::ZERO);
+}
+
+#[test]
+fn taubin_smoothing_shrinks_less() {
+    let subdivide = Command::Subdivide {
+        levels: 2,
+        scheme: Subdivision::Midpoint,
+        target: Target::Region,
+    };
+    let (laplacian, _) = run(&[
+        subdivide.clone(),
+        smooth(10, 0.5, Smoothing::Laplacian, Target::Region),
+    ]);
+    let (taubin, _) = run(&[
+        subdivide,
+        smooth(10, 0.5, Smoothing::Taubin, Target::Region),
+    ]);
+
+    assert!(spread(&taubin) > 2.0 * spread(&laplacian));
+    assert!(taubin.validate().is_valid());
+}
+
+#[test]
+fn smooth_limits_the_iterations() {
+    let (mut mesh, mut selections) = tetrahedron();
+    assert_eq!(
+        smooth(1001, 0.5, Smoothing::Laplacian, Target::Region).call(&mut mesh, &mut selections),
+        Err(CommandError::SmoothIterationsOutOfRange(1001))
+    );
+    assert_eq!(mesh.positions, Mesh::new().positions);
+}
+
+#[test]
+fn smooth_moves_tied_vertices_together() {
+    let (mut mesh, mut selections) = tetrahedron();
+    // Give the first corner its own copy of vertex 0, the way a seam in the colors would
+    let tied = mesh.add_vertex(mesh.positions[0]);
+    mesh.colors[tied] = Color::from_rgb(1.0, 0.0, 0.0);
+    mesh.indices[0] = tied as i32;
+
+    smooth(1, 1.0, Smoothing::Laplacian, Target::Vertex)
+        .call(&mut mesh, &mut selections)
+        .unwrap();
+
+    let third = 1.0 / 3.0;
+    assert_near(mesh.positions[0], Vec3::new(third, third, third));
+    assert_eq!(mesh.positions[tied], mesh.positions[0]);
+}
+
+#[test]
+fn smooth_rejects_factors_outside_0_to_1() {
+    let (mut mesh, mut selections) = tetrahedron();
+    assert_eq!(
+        smooth(1, 1.5, Smoothing::Taubin, Target::Region).call(&mut mesh, &mut selections),
+        Err(CommandError::SmoothFactorOutOfRange(1.5))
+    );
+}
+
+#[test]
+fn poke_splits_the_face_into_three() {
+    let (mesh, _) = run(&[Command::Poke(Vec3::new(1.0, 1.0, 1.0))]);
+
+    let third = 1.0 / 3.0;
+    assert_eq!(mesh.face_count(), 6);
+    assert_near(mesh.positions[4], Vec3::new(0.0, third, third));
+    assert_connections_consistent(&mesh);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn poke_keeps_the_selection_on_its_edge() {
+    let (mesh, selections) = run(&[
+        Command::MoveEdgeSelection,
+        Command::Poke(Vec3::new(0.5, 0.25, 0.25)),
+    ]);
+
+    // The selection ran from vertex 2 to vertex 1 and is now on the face along that edge
+    assert_eq!(mesh.get_meta_index(selections[0]), Ok(12));
+    assert_eq!(
+        mesh.get_face_positions(selections[0]).unwrap(),
+        [
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.5, 0.25),
+        ]
+    );
+}
+
+#[test]
+fn poke_needs_weights_inside_the_face() {
+    let (mut mesh, mut selections) = tetrahedron();
+    for weights in [Vec3::new(1.0, -0.5, 0.5), Vec3::ZERO] {
+        assert_eq!(
+            Command::Poke(weights).call(&mut mesh, &mut selections),
+            Err(CommandError::PokeOutsideFace(weights))
+        );
+    }
+    assert_eq!(mesh.face_count(), 4);
+}
+
+#[test]
+fn poke_rejects_nan_weights() {
+    let (mut mesh, mut selections) = tetrahedron();
+    let poke = parse_script("poke NaN 1 1").unwrap().remove(0);
+    assert!(matches!(
+        poke.call(&mut mesh, &mut selections),
+        Err(CommandError::PokeOutsideFace(weights)) if weights.x.is_nan()
+    ));
+    let infinite = Command::Poke(Vec3::new(f32::INFINITY, 1.0, 1.0));
+    assert!(infinite.call(&mut mesh, &mut selections).is_err());
+    assert_eq!(mesh.face_count(), 4);
+}
+
+#[test]
+fn translate_after_pull_only_moves_the_new_vertex() {
+    let delta = Vec3::new(0.0, 0.0, -1.0);
+    let (mesh, _) = run(&[
+        Command::Pull,
+        Command::Translate {
+            delta,
+            space: Space::World,
+        },
+    ]);
+
+    assert_eq!(mesh.positions[0], Vec3::ZERO);
+    assert_eq!(mesh.positions[4], delta);
+    assert_connections_consistent(&mesh);
+}
+
+#[test]
+fn translate_moves_the_selected_vertex() {
+    let delta = Vec3::new(1.0, 2.0, 3.0);
+    let (mesh, _) = run(&[
+        Command::MoveEdgeSelection,
+        Command::Translate {
+            delta,
+            space: Space::World,
+        },
+    ]);
+
+    // Meta index 1 is vertex 2
+    assert_eq!(mesh.positions[2], Vec3::new(0.0, 1.0, 0.0) + delta);
+    assert_eq!(mesh.positions[0], Vec3::ZERO);
+}
+
+#[test]
+fn color_paints_the_selected_vertex() {
+    let red = Color::from_rgb(1.0, 0.0, 0.0);
+    let (mesh, _) = run(&[Command::Color(red)]);
+
+    assert_eq!(mesh.colors[0], red);
+    assert_eq!(mesh.colors[1], Color::default());
+}
+
+#[test]
+fn split_rejects_amounts_outside_the_edge() {
+    let (mut mesh, mut selections) = tetrahedron();
+    let before = (mesh.positions.clone(), mesh.indices.clone());
+
+    assert_eq!(
+        Command::Split(1.5).call(&mut mesh, &mut selections),
+        Err(CommandError::SplitAmountOutOfRange(1.5))
+    );
+    assert!(
+        Command::Split(f32::NAN)
+            .call(&mut mesh, &mut selections)
+            .is_err()
+    );
+    assert_eq!((mesh.positions, mesh.indices), before);
+}
+
+#[test]
+fn the_last_selection_cannot_be_popped() {
+    let (mut mesh, mut selections) = tetrahedron();
+
+    assert_eq!(
+        Command::PopSelection.call(&mut mesh, &mut selections),
+        Err(CommandError::PopLastSelection)
+    );
+    assert_eq!(selections.len(), 1);
+    assert_eq!(
+        Command::Pull.call(&mut mesh, &mut Vec::new()),
+        Err(CommandError::NoSelection)
+    );
+}
+
+#[test]
+fn tracking_past_the_last_corner_fails() {
+    let mut mesh = Mesh::new();
+    assert_eq!(
+        mesh.track_index(12),
+        Err(CommandError::MetaIndexOutOfRange(12))
+    );
+    assert_eq!(
+        mesh.get_meta_index(0),
+        Err(CommandError::UntrackedSelection(0))
+    );
+}
@@ -0,0 +1,151 @@
+use rizmo_kernel::document::FORMAT_VERSION;
+use rizmo_kernel::{
+    Color, Command, CommandStack, Document, DocumentError, Mesh, Primitive, Space, Subdivision,
+    Target, Vec3,
+};
+
+fn expected_commands() -> Vec<Command> {
+    vec![
+        Command::Split(0.5),
+        Command::MoveFaceSelection,
+        Command::Pull,
+        Command::Translate {
+            delta: Vec3::new(0.0, 0.0, -1.0),
+            space: Space::World,
+        },
+        Command::Color(Color::from_rgb(1.0, 0.0, 0.0)),
+    ]
+}
+
+fn replay(document: Document) -> Mesh {
+    let mut stack = CommandStack::new();
+    stack.extend_from_document(document);
+    let mut mesh = Mesh::new();
+    stack.replay(&mut mesh, &mut Vec::new()).unwrap();
+    mesh
+}
+
+#[test]
+fn version_0_id_maps_load_in_id_order() {
+    let document = Document::from_json(include_str!("fixtures/v0.json")).unwrap();
+    assert_eq!(document.commands, expected_commands());
+    assert!(document.base.is_none());
+    assert_eq!(document.app_version, None);
+    assert_eq!(replay(document).face_count(), 8);
+}
+
+#[test]
+fn version_1_arrays_load() {
+    let document = Document::from_json(include_str!("fixtures/v1.json")).unwrap();
+    assert_eq!(document.commands, expected_commands());
+    assert!(document.base.is_none());
+    assert_eq!(replay(document).face_count(), 8);
+}
+
+#[test]
+fn version_2_base_meshes_load() {
+    let document = Document::from_json(include_str!("fixtures/v2.json")).unwrap();
+    assert_eq!(document.commands, expected_commands());
+    assert_eq!(document.base.as_ref().unwrap().face_count(), 8);
+    assert!(document.metadata.is_empty());
+    assert_eq!(replay(document).face_count(), 12);
+}
+
+#[test]
+fn version_3_documents_load() {
+    let document = Document::from_json(include_str!("fixtures/v3.json")).unwrap();
+    assert_eq!(document.commands, expected_commands());
+    assert_eq!(document.app_version.as_deref(), Some("0.1.0"));
+    assert_eq!(document.metadata["author"], "gizmo");
+    assert_eq!(replay(document).face_count(), 12);
+}
+
+#[test]
+fn version_4_documents_load() {
+    let document = Document::from_json(include_str!("fixtures/v4.json")).unwrap();
+    assert_eq!(document.commands, expected_commands());
+    assert_eq!(document.primitive, Primitive::Tetrahedron);
+    assert_eq!(replay(document).face_count(), 12);
+}
+
+#[test]
+fn version_5_primitives_load() {
+    let document = Document::from_json(include_str!("fixtures/v5.json")).unwrap();
+    assert_eq!(document.commands, expected_commands());
+    assert!(document.base.is_none());
+    assert_eq!(document.primitive, Primitive::Octahedron);
+    assert_eq!(replay(document).face_count(), 12);
+}
+
+#[test]
+fn version_5_subdivisions_split_the_whole_region() {
+    let json = r#"{"format_version":5,"commands":[{"Subdivide":{"levels":1,"scheme":"Loop"}}]}"#;
+    let document = Document::from_json(json).unwrap();
+    assert_eq!(
+        document.commands,
+        vec![Command::Subdivide {
+            levels: 1,
+            scheme: Subdivision::Loop,
+            target: Target::Region,
+        }]
+    );
+    assert_eq!(replay(document).face_count(), 16);
+}
+
+#[test]
+fn documents_round_trip() {
+    let mut stack = CommandStack::new();
+    for command in expected_commands() {
+        stack.push(command);
+    }
+    stack.set_primitive(Primitive::Torus {
+        segments: 12,
+        sides: 6,
+        thickness: 0.25,
+    });
+    stack
+        .metadata_mut()
+        .insert("description".into(), "a torus".into());
+    let json = stack.to_json().unwrap();
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["format_version"], FORMAT_VERSION);
+    let document = Document::from_json(&json).unwrap();
+    assert_eq!(document.commands, expected_commands());
+    assert_eq!(document.primitive, stack.primitive());
+    assert_eq!(document.metadata["description"], "a torus");
+}
+
+#[test]
+fn base_meshes_with_holes_round_trip() {
+    let mut base = Mesh::new();
+    let mut selections = vec![base.track_index(0).unwrap()];
+    Command::DeleteFace
+        .call(&mut base, &mut selections)
+        .unwrap();
+    let mut stack = CommandStack::new();
+    stack.set_base(Some(base));
+    stack.push(Command::Split(0.5));
+    let json = stack.to_json().unwrap();
+
+    let mesh = replay(Document::from_json(&json).unwrap());
+    assert_eq!(mesh.face_count(), 5);
+    let boundary = mesh
+        .connections
+        .iter()
+        .filter(|&&twin| twin == Mesh::BOUNDARY);
+    assert_eq!(boundary.count(), 3);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn newer_versions_are_refused() {
+    let json = format!(
+        r#"{{"format_version":{},"commands":[]}}"#,
+        FORMAT_VERSION + 1
+    );
+    assert!(matches!(
+        Document::from_json(&json),
+        Err(DocumentError::NewerVersion(version)) if version == FORMAT_VERSION + 1
+    ));
+}
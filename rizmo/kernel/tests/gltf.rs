@@ -0,0 +1,79 @@
+use rizmo_kernel::io::gltf::{write_glb, write_gltf};
+use rizmo_kernel::{Command, Mesh};
+use serde_json::Value;
+
+fn split_tetrahedron() -> Mesh {
+    let mut mesh = Mesh::new();
+    let mut selections = vec![mesh.track_index(0).unwrap()];
+    Command::Split(0.5)
+        .call(&mut mesh, &mut selections)
+        .unwrap();
+    mesh
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[test]
+fn gltf_embeds_the_buffer() {
+    let mut gltf = Vec::new();
+    write_gltf(&split_tetrahedron(), &mut gltf).unwrap();
+    let document: Value = serde_json::from_slice(&gltf).unwrap();
+
+    assert_eq!(document["asset"]["version"], "2.0");
+    let uri = document["buffers"][0]["uri"].as_str().unwrap();
+    assert!(uri.starts_with("data:application/octet-stream;base64,"));
+
+    let accessors = document["accessors"].as_array().unwrap();
+    assert_eq!(accessors[0]["count"], 5);
+    assert_eq!(accessors[1]["count"], 5);
+    assert_eq!(accessors[2]["count"], 5);
+    assert_eq!(accessors[3]["count"], 18);
+    assert_eq!(accessors[0]["min"], serde_json::json!([0.0, 0.0, 0.0]));
+    assert_eq!(accessors[0]["max"], serde_json::json!([1.0, 1.0, 1.0]));
+}
+
+#[test]
+fn glb_chunks_are_aligned() {
+    let mut glb = Vec::new();
+    write_glb(&split_tetrahedron(), &mut glb).unwrap();
+
+    assert_eq!(&glb[0..4], b"glTF");
+    assert_eq!(u32_at(&glb, 4), 2);
+    assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+
+    let json_length = u32_at(&glb, 12) as usize;
+    assert_eq!(&glb[16..20], b"JSON");
+    assert_eq!(json_length % 4, 0);
+    let document: Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+    assert!(document["buffers"][0].get("uri").is_none());
+
+    let bin = 20 + json_length;
+    assert_eq!(&glb[bin + 4..bin + 8], b"BIN\0");
+    let bin_length = u32_at(&glb, bin) as usize;
+    assert_eq!(bin_length % 4, 0);
+    assert!(bin_length >= document["buffers"][0]["byteLength"].as_u64().unwrap() as usize);
+    assert_eq!(bin + 8 + bin_length, glb.len());
+}
+
+#[test]
+fn normals_point_out_of_the_tetrahedron() {
+    let mut glb = Vec::new();
+    write_glb(&Mesh::new(), &mut glb).unwrap();
+    let json_length = u32_at(&glb, 12) as usize;
+    let document: Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+    let bin = &glb[28 + json_length..];
+
+    let view =
+        &document["bufferViews"][document["accessors"][1]["bufferView"].as_u64().unwrap() as usize];
+    let offset = view["byteOffset"].as_u64().unwrap() as usize;
+    let floats: Vec<f32> = bin[offset..offset + 48]
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+        .collect();
+    // The vertex at the origin has to point away from the other three
+    assert!(floats[0] < 0.0 && floats[1] < 0.0 && floats[2] < 0.0);
+    // And the vertex at (1, 0, 0) along +x
+    assert!(floats[9] > 0.0);
+}
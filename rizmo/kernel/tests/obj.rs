@@ -0,0 +1,202 @@
+use std::io::Cursor;
+
+use rizmo_kernel::io::obj::{ObjError, read_obj, write_mtl, write_obj};
+use rizmo_kernel::{Color, Command, CommandStack, Mesh, MeshError, Space, Vec3};
+
+// A unit cube where every face has its own four vertices, wound counter-clockwise
+const CUBE: &str = "
+o cube
+v 0 0 0
+v 0 1 0
+v 1 1 0
+v 1 0 0
+f 1 2 3 4
+v 0 0 1
+v 1 0 1
+v 1 1 1
+v 0 1 1
+f 5 6 7 8
+v 0 0 0
+v 1 0 0
+v 1 0 1
+v 0 0 1
+f -4 -3 -2 -1
+v 0 1 0
+v 0 1 1
+v 1 1 1
+v 1 1 0
+f 13/1 14/2 15/3 16/4
+v 0 0 0
+v 0 0 1
+v 0 1 1
+v 0 1 0
+f 17//1 18//1 19//1 20//1
+v 1 0 0
+v 1 1 0
+v 1 1 1
+v 1 0 1
+f 21 22 23 24
+";
+
+fn export(mesh: &Mesh) -> String {
+    let mut obj = Vec::new();
+    write_obj(mesh, &mut obj, Some("model.mtl")).unwrap();
+    String::from_utf8(obj).unwrap()
+}
+
+fn lines<'a>(obj: &'a str, prefix: &str) -> Vec<&'a str> {
+    obj.lines()
+        .filter(|line| line.starts_with(prefix))
+        .collect()
+}
+
+#[test]
+fn tetrahedron_exports_four_vertices_and_faces() {
+    let mut mesh = Mesh::new();
+    let selection = mesh.track_index(0).unwrap();
+    Command::Color(Color::from_rgb(1.0, 0.0, 0.0))
+        .call(&mut mesh, &mut vec![selection])
+        .unwrap();
+
+    let obj = export(&mesh);
+    assert_eq!(
+        lines(&obj, "v "),
+        vec![
+            "v 0 0 0 1 0 0",
+            "v 0 0 1 0 0 0",
+            "v 0 1 0 0 0 0",
+            "v 1 0 0 0 0 0"
+        ]
+    );
+    assert_eq!(
+        lines(&obj, "f "),
+        vec!["f 1 2 3", "f 1 4 2", "f 1 3 4", "f 4 3 2"]
+    );
+    assert_eq!(lines(&obj, "mtllib "), vec!["mtllib model.mtl"]);
+    assert_eq!(lines(&obj, "usemtl "), vec!["usemtl gizmo"]);
+}
+
+#[test]
+fn spare_capacity_is_not_exported() {
+    let mut mesh = Mesh::new();
+    let mut selections = vec![mesh.track_index(0).unwrap()];
+    Command::Split(0.5)
+        .call(&mut mesh, &mut selections)
+        .unwrap();
+
+    let obj = export(&mesh);
+    assert_eq!(lines(&obj, "v ").len(), 5);
+    assert_eq!(lines(&obj, "f ").len(), 6);
+}
+
+#[test]
+fn tied_vertices_are_welded() {
+    let mut mesh = Mesh::new();
+    // Give the fan around vertex 0 a second slot at the same position
+    let tied = mesh.add_vertex(Vec3::ZERO) as i32;
+    mesh.indices[3] = tied;
+
+    let obj = export(&mesh);
+    assert_eq!(lines(&obj, "v ").len(), 4);
+    assert_eq!(lines(&obj, "f ")[1], "f 1 4 2");
+}
+
+#[test]
+fn material_library_defines_the_material() {
+    let mut mtl = Vec::new();
+    write_mtl(&mut mtl).unwrap();
+    assert!(String::from_utf8(mtl).unwrap().contains("newmtl gizmo"));
+}
+
+#[test]
+fn imported_polygons_are_welded_and_triangulated() {
+    let (mesh, report) = read_obj(Cursor::new(CUBE), false).unwrap();
+    assert_eq!(mesh.vertex_count(), 8);
+    assert_eq!(mesh.face_count(), 12);
+    assert_eq!(report.welded_vertices, 16);
+    assert!(report.is_clean());
+    assert!(mesh.validate().is_valid());
+    // The bottom face points down once it's wound clockwise
+    assert_eq!(mesh.face_normal(0), Vec3::new(0.0, 0.0, -1.0));
+}
+
+#[test]
+fn exports_import_again() {
+    let mut mesh = Mesh::new();
+    let mut selections = vec![mesh.track_index(0).unwrap()];
+    for command in [
+        Command::Split(0.5),
+        Command::Pull,
+        Command::Translate {
+            delta: Vec3::new(0.0, -1.0, 0.0),
+            space: Space::World,
+        },
+    ] {
+        command.call(&mut mesh, &mut selections).unwrap();
+    }
+
+    let (imported, _) = read_obj(Cursor::new(export(&mesh)), false).unwrap();
+    assert_eq!(imported.vertex_count(), mesh.vertex_count());
+    assert_eq!(imported.face_count(), mesh.face_count());
+}
+
+#[test]
+fn inside_out_faces_are_repaired_or_rejected() {
+    let flipped = CUBE.replace("f 21 22 23 24", "f 24 23 22 21");
+    assert!(matches!(
+        read_obj(Cursor::new(&flipped), false),
+        Err(ObjError::Mesh(MeshError::NonManifoldEdge { .. }))
+    ));
+
+    let (mesh, report) = read_obj(Cursor::new(&flipped), true).unwrap();
+    assert_eq!(report.flipped_faces, 2);
+    assert!(mesh.validate().is_valid());
+    assert_eq!(mesh.face_normal(30), Vec3::new(1.0, 0.0, 0.0));
+}
+
+#[test]
+fn repeated_and_collapsed_faces_are_dropped() {
+    let obj = format!("{CUBE}\nf 1 2 3\nf 1 9 2\n");
+    let (mesh, report) = read_obj(Cursor::new(obj), true).unwrap();
+    assert_eq!(report.duplicate_faces, 1);
+    assert_eq!(report.degenerate_faces, 1);
+    assert_eq!(mesh.face_count(), 12);
+}
+
+#[test]
+fn open_meshes_keep_their_boundary() {
+    let open = CUBE.replace("f 21 22 23 24", "");
+    let (mesh, _) = read_obj(Cursor::new(&open), true).unwrap();
+    assert_eq!(mesh.face_count(), 10);
+    let boundary = mesh
+        .connections
+        .iter()
+        .filter(|&&twin| twin == Mesh::BOUNDARY);
+    assert_eq!(boundary.count(), 4);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn syntax_errors_have_line_numbers() {
+    let obj = "v 0 0 0\nv 1 0 0\nf 1 2 3\n";
+    match read_obj(Cursor::new(obj), false) {
+        Err(ObjError::Syntax { line, .. }) => assert_eq!(line, 3),
+        other => panic!("expected a syntax error, got {other:?}"),
+    }
+}
+
+#[test]
+fn saved_stacks_carry_the_base_mesh() {
+    let (base, _) = read_obj(Cursor::new(CUBE), false).unwrap();
+    let mut stack = CommandStack::new();
+    stack.set_base(Some(base));
+    stack.push(Command::Split(0.5));
+    let json = stack.to_json().unwrap();
+
+    let mut loaded = CommandStack::new();
+    loaded.extend_from_json(&json).unwrap();
+    let mut mesh = Mesh::new();
+    loaded.replay(&mut mesh, &mut Vec::new()).unwrap();
+    assert_eq!(mesh.vertex_count(), 9);
+    assert_eq!(mesh.face_count(), 14);
+}
@@ -0,0 +1,235 @@
+use std::io::Cursor;
+
+use rizmo_kernel::io::ply::{PlyError, PlyFormat, read_ply, write_ply};
+use rizmo_kernel::{Color, Command, Mesh, Space, Vec3};
+
+fn colored_mesh() -> Mesh {
+    let mut mesh = Mesh::new();
+    let mut selections = vec![mesh.track_index(0).unwrap()];
+    for command in [
+        Command::Split(0.5),
+        Command::Color(Color::from_rgba(1.0, 0.0, 0.0, 1.0)),
+        Command::MoveFaceSelection,
+        Command::Pull,
+        Command::Translate {
+            delta: Vec3::new(0.0, 0.0, -1.0),
+            space: Space::World,
+        },
+        Command::Color(Color::from_rgba(0.0, 0.0, 1.0, 0.2)),
+    ] {
+        command.call(&mut mesh, &mut selections).unwrap();
+    }
+    mesh
+}
+
+fn round_trip(mesh: &Mesh, format: PlyFormat) -> Mesh {
+    let mut ply = Vec::new();
+    write_ply(mesh, &mut ply, format).unwrap();
+    read_ply(Cursor::new(ply)).unwrap()
+}
+
+fn assert_same_surface(a: &Mesh, b: &Mesh) {
+    assert_eq!(a.vertex_count(), b.vertex_count());
+    assert_eq!(a.face_count(), b.face_count());
+    let mut a_vertices: Vec<String> = (0..a.vertex_count())
+        .map(|i| format!("{:?} {:?}", a.positions[i], a.colors[i]))
+        .collect();
+    let mut b_vertices: Vec<String> = (0..b.vertex_count())
+        .map(|i| format!("{:?} {:?}", b.positions[i], b.colors[i]))
+        .collect();
+    a_vertices.sort();
+    b_vertices.sort();
+    assert_eq!(a_vertices, b_vertices);
+}
+
+#[test]
+fn ascii_round_trips() {
+    let mesh = colored_mesh();
+    let imported = round_trip(&mesh, PlyFormat::Ascii);
+    assert_same_surface(&mesh, &imported);
+    assert!(imported.validate().is_valid());
+}
+
+#[test]
+fn binary_round_trips() {
+    let mesh = colored_mesh();
+    let imported = round_trip(&mesh, PlyFormat::BinaryLittleEndian);
+    assert_same_surface(&mesh, &imported);
+    assert!(imported.validate().is_valid());
+}
+
+#[test]
+fn imported_meshes_can_be_edited() {
+    let mut mesh = round_trip(&colored_mesh(), PlyFormat::Ascii);
+    let mut selections = vec![mesh.track_index(0).unwrap()];
+    for command in [
+        Command::MoveFaceSelection,
+        Command::Split(0.5),
+        Command::Pull,
+    ] {
+        command.call(&mut mesh, &mut selections).unwrap();
+    }
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn quads_are_triangulated() {
+    // A cube with its faces wound counter-clockwise
+    let ply = "ply
+format ascii 1.0
+element vertex 8
+property float x
+property float y
+property float z
+element face 6
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+1 1 0
+0 1 0
+0 0 1
+1 0 1
+1 1 1
+0 1 1
+4 0 3 2 1
+4 4 5 6 7
+4 0 1 5 4
+4 1 2 6 5
+4 2 3 7 6
+4 3 0 4 7
+";
+    let mesh = read_ply(Cursor::new(ply)).unwrap();
+    assert_eq!(mesh.face_count(), 12);
+    assert_eq!(mesh.vertex_count(), 8);
+    assert_eq!(mesh.colors[0], Color::default());
+    // Faces come out clockwise, so the bottom face points down
+    assert_eq!(mesh.face_normal(0), Vec3::new(0.0, 0.0, -1.0));
+}
+
+#[test]
+fn split_vertices_are_welded() {
+    // A cube with its own four vertices per face, as exporters write it when every face has its
+    // own color
+    let corners = [
+        [0, 3, 2, 1],
+        [4, 5, 6, 7],
+        [0, 1, 5, 4],
+        [1, 2, 6, 5],
+        [2, 3, 7, 6],
+        [3, 0, 4, 7],
+    ];
+    let position = |corner: usize| [corner & 1 ^ corner >> 1 & 1, corner >> 1 & 1, corner >> 2];
+    let mut ply = String::from(
+        "ply
+format ascii 1.0
+element vertex 24
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 6
+property list uchar int vertex_indices
+end_header
+",
+    );
+    for (face, face_corners) in corners.iter().enumerate() {
+        for &corner in face_corners {
+            let [x, y, z] = position(corner);
+            ply += &format!("{x} {y} {z} {} 0 0\n", face * 40);
+        }
+    }
+    for face in 0..6 {
+        let first = face * 4;
+        ply += &format!("4 {} {} {} {}\n", first, first + 1, first + 2, first + 3);
+    }
+
+    let mesh = read_ply(Cursor::new(ply)).unwrap();
+    assert_eq!(mesh.face_count(), 12);
+    assert_eq!(mesh.vertex_count(), 8);
+    assert!(!mesh.connections.contains(&Mesh::BOUNDARY));
+    assert!(mesh.validate().is_valid());
+    assert_eq!(mesh.face_normal(0), Vec3::new(0.0, 0.0, -1.0));
+}
+
+#[test]
+fn open_surfaces_keep_their_boundary() {
+    let ply = "ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+0 1 0
+3 0 1 2
+";
+    let mesh = read_ply(Cursor::new(ply)).unwrap();
+    assert_eq!(mesh.face_count(), 1);
+    assert_eq!(mesh.connections, vec![Mesh::BOUNDARY; 3]);
+}
+
+#[test]
+fn big_endian_is_unsupported() {
+    let ply = "ply\nformat binary_big_endian 1.0\nend_header\n";
+    assert!(matches!(
+        read_ply(Cursor::new(ply)),
+        Err(PlyError::Header(_))
+    ));
+}
+
+#[test]
+fn short_bodies_are_reported() {
+    let mut ply = Vec::new();
+    write_ply(&Mesh::new(), &mut ply, PlyFormat::BinaryLittleEndian).unwrap();
+    ply.truncate(ply.len() - 5);
+    assert!(matches!(read_ply(Cursor::new(ply)), Err(PlyError::Body(_))));
+}
+
+// A tetrahedron with the given line in place of its last face
+fn tetrahedron_ply(index_type: &str, last_face: &str) -> String {
+    format!(
+        "ply
+format ascii 1.0
+element vertex 4
+property float x
+property float y
+property float z
+element face 4
+property list uint {index_type} vertex_indices
+end_header
+0 0 0
+0 0 1
+0 1 0
+1 0 0
+3 0 1 2
+3 0 3 1
+3 0 2 3
+{last_face}
+"
+    )
+}
+
+#[test]
+fn huge_list_lengths_run_out_of_values() {
+    let ply = tetrahedron_ply("int", "4294967295 3 2 1");
+    assert!(matches!(read_ply(Cursor::new(ply)), Err(PlyError::Body(_))));
+}
+
+#[test]
+fn fractional_and_nan_indices_are_rejected() {
+    assert!(read_ply(Cursor::new(tetrahedron_ply("float", "3 3 2 1"))).is_ok());
+    for last_face in ["3 3 2.5 1", "3 3 nan 1", "3 3 -1 1", "3 3 inf 1"] {
+        let ply = tetrahedron_ply("float", last_face);
+        assert!(
+            matches!(read_ply(Cursor::new(ply)), Err(PlyError::Body(_))),
+            "{last_face}"
+        );
+    }
+}
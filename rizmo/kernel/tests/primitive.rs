@@ -0,0 +1,133 @@
+use rizmo_kernel::primitive::{MAX_SEGMENTS, MAX_TORUS_THICKNESS, MIN_TORUS_THICKNESS};
+use rizmo_kernel::{Mesh, Primitive, Vec3};
+
+fn torus_thickness(primitive: Primitive) -> Option<f32> {
+    match primitive {
+        Primitive::Torus { thickness, .. } => Some(thickness),
+        _ => None,
+    }
+}
+
+// The point each face should face away from: the middle, or the middle of the tube for a torus
+fn inside(primitive: Primitive, centroid: Vec3) -> Vec3 {
+    match torus_thickness(primitive) {
+        Some(thickness) => {
+            let ring_radius = 0.5 / (1.0 + thickness);
+            Vec3::new(centroid.x, 0.0, centroid.z).normalized() * ring_radius
+        }
+        None if primitive == Primitive::Tetrahedron => Vec3::new(0.25, 0.25, 0.25),
+        None => Vec3::ZERO,
+    }
+}
+
+#[test]
+fn primitives_are_closed_and_face_outward() {
+    for primitive in Primitive::ALL {
+        let mesh = primitive.to_mesh();
+        assert!(mesh.validate().is_valid(), "{primitive:?}");
+        assert!(!mesh.connections.contains(&Mesh::BOUNDARY), "{primitive:?}");
+
+        // Spheres have an Euler characteristic of 2 and tori 0
+        let edges = mesh.indices.len() / 2;
+        let euler = mesh.vertex_count() as i32 - edges as i32 + mesh.face_count() as i32;
+        let expected = match torus_thickness(primitive) {
+            Some(_) => 0,
+            None => 2,
+        };
+        assert_eq!(euler, expected, "{primitive:?}");
+
+        for start in (0..mesh.indices.len()).step_by(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[mesh.indices[start + i] as usize]);
+            let centroid = (a + b + c) / 3.0;
+            let outward = centroid - inside(primitive, centroid);
+            assert!(
+                mesh.face_normal(start).dot(outward) > 0.0,
+                "{primitive:?} face {}",
+                start / 3
+            );
+        }
+    }
+}
+
+#[test]
+fn primitives_fit_in_a_unit_box() {
+    for primitive in &Primitive::ALL[1..] {
+        let mesh = primitive.to_mesh();
+        for position in &mesh.positions[..mesh.vertex_count()] {
+            for axis in [position.x, position.y, position.z] {
+                assert!(axis.abs() <= 0.5 + 1e-6, "{primitive:?} {position:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn parameters_set_the_detail() {
+    assert_eq!(Primitive::Cube.to_mesh().face_count(), 12);
+    assert_eq!(Primitive::Octahedron.to_mesh().face_count(), 8);
+    assert_eq!(
+        Primitive::Icosphere { subdivisions: 2 }
+            .to_mesh()
+            .face_count(),
+        320
+    );
+    assert_eq!(
+        Primitive::UvSphere {
+            segments: 8,
+            rings: 4
+        }
+        .to_mesh()
+        .face_count(),
+        48
+    );
+    assert_eq!(Primitive::Cone { segments: 5 }.to_mesh().face_count(), 10);
+    // Too few sides to close up is raised to the fewest that do
+    assert_eq!(
+        Primitive::Cylinder { segments: 1 }.to_mesh().face_count(),
+        12
+    );
+}
+
+#[test]
+fn parameters_are_kept_in_range() {
+    let huge = Primitive::Cylinder { segments: u32::MAX };
+    assert_eq!(huge.to_mesh().face_count(), 4 * MAX_SEGMENTS as usize);
+
+    // The ring is sized from the thickness, so a thickness out of range changes how big it is
+    let outer_radius = |thickness: f32| {
+        let mesh = Primitive::Torus {
+            segments: 8,
+            sides: 4,
+            thickness,
+        }
+        .to_mesh();
+        mesh.positions[..mesh.vertex_count()]
+            .iter()
+            .map(|position| position.x.hypot(position.z))
+            .fold(0.0, f32::max)
+    };
+    assert_eq!(outer_radius(f32::NAN), outer_radius(0.5));
+    assert_eq!(outer_radius(-1.0), outer_radius(MIN_TORUS_THICKNESS));
+    assert_eq!(
+        outer_radius(f32::INFINITY),
+        outer_radius(MAX_TORUS_THICKNESS)
+    );
+    for thickness in [f32::NAN, -1.0, 0.0, f32::INFINITY] {
+        let mesh = Primitive::Torus {
+            segments: 8,
+            sides: 4,
+            thickness,
+        }
+        .to_mesh();
+        assert!(mesh.validate().is_valid(), "{thickness}");
+        assert!(mesh.positions.iter().all(|position| position.x.is_finite()));
+    }
+}
+
+#[test]
+fn names_round_trip() {
+    for primitive in Primitive::ALL {
+        assert_eq!(Primitive::from_name(primitive.name()), Some(primitive));
+    }
+    assert_eq!(Primitive::from_name("teapot"), None);
+}
@@ -0,0 +1,166 @@
+use rizmo_kernel::{
+    Color, Command, CommandStack, Mesh, Pivot, Smoothing, Space, Subdivision, Target, Vec3,
+    parse_script, print_script,
+};
+
+#[test]
+fn parses_every_command() {
+    let script = "
+// A comment on its own line
+push
+pop
+face
+edge   // and one after a command
+translate 1 0 -2.5
+translate 0 0 0.2 face
+scale 2 1 1
+rotate 0 0 1 90 origin region
+split 0.5
+poke 1 1 2
+pull
+extrude -0.25
+inset 0.1
+collapse 0.5
+flip
+delete
+fill
+subdivide 2
+subdivide 1 midpoint face
+smooth 3 0.5 taubin region
+color #ff0000
+color #00ff0080
+color 0.1 0.2 0.3
+";
+    assert_eq!(
+        parse_script(script).unwrap(),
+        vec![
+            Command::PushSelection,
+            Command::PopSelection,
+            Command::MoveFaceSelection,
+            Command::MoveEdgeSelection,
+            Command::Translate {
+                delta: Vec3::new(1.0, 0.0, -2.5),
+                space: Space::World,
+            },
+            Command::Translate {
+                delta: Vec3::new(0.0, 0.0, 0.2),
+                space: Space::Face,
+            },
+            Command::Scale {
+                factor: Vec3::new(2.0, 1.0, 1.0),
+                pivot: Pivot::FaceCentroid,
+                target: Target::Face,
+            },
+            Command::Rotate {
+                axis: Vec3::new(0.0, 0.0, 1.0),
+                angle: 90.0,
+                pivot: Pivot::Origin,
+                target: Target::Region,
+            },
+            Command::Split(0.5),
+            Command::Poke(Vec3::new(1.0, 1.0, 2.0)),
+            Command::Pull,
+            Command::Extrude(-0.25),
+            Command::Inset(0.1),
+            Command::EdgeCollapse(0.5),
+            Command::FlipEdge,
+            Command::DeleteFace,
+            Command::FillHole,
+            Command::Subdivide {
+                levels: 2,
+                scheme: Subdivision::Loop,
+                target: Target::Region,
+            },
+            Command::Subdivide {
+                levels: 1,
+                scheme: Subdivision::Midpoint,
+                target: Target::Face,
+            },
+            Command::Smooth {
+                iterations: 3,
+                factor: 0.5,
+                mode: Smoothing::Taubin,
+                target: Target::Region,
+            },
+            Command::Color(Color::from_rgba(1.0, 0.0, 0.0, 1.0)),
+            Command::Color(Color::from_rgba(0.0, 1.0, 0.0, 128.0 / 255.0)),
+            Command::Color(Color::from_rgba(0.1, 0.2, 0.3, 1.0)),
+        ]
+    );
+}
+
+#[test]
+fn printing_is_lossless() {
+    let commands = vec![
+        Command::Split(1.0 / 3.0),
+        Command::Translate {
+            delta: Vec3::new(0.1, -0.0, 1e-7),
+            space: Space::World,
+        },
+        Command::Translate {
+            delta: Vec3::new(0.0, 0.5, 1.0),
+            space: Space::Face,
+        },
+        Command::Color(Color::from_rgba(1.0, 0.5, 0.0, 1.0)),
+        Command::Color(Color::from_rgba(51.0 / 255.0, 0.0, 1.0, 0.2)),
+        Command::MoveEdgeSelection,
+        Command::PushSelection,
+    ];
+    let script = print_script(&commands);
+    let parsed = parse_script(&script).unwrap();
+    assert_eq!(format!("{parsed:?}"), format!("{commands:?}"));
+    assert_eq!(print_script(&parsed), script);
+}
+
+#[test]
+fn exact_colors_print_as_hex() {
+    let script = print_script(&[
+        Command::Color(Color::from_rgba(1.0, 0.0, 0.0, 1.0)),
+        Command::Color(Color::from_rgba(0.0, 0.0, 1.0, 0.2)),
+    ]);
+    assert_eq!(script, "color #ff0000\ncolor #0000ff33\n");
+}
+
+#[test]
+fn errors_point_at_the_problem() {
+    let error = parse_script("push\n  translate 1 x 0\n").unwrap_err();
+    assert_eq!((error.line, error.column), (2, 15));
+    assert_eq!(error.to_string(), "2:15: expected a number, found \"x\"");
+
+    let error = parse_script("split").unwrap_err();
+    assert_eq!((error.line, error.column), (1, 6));
+    assert_eq!(error.message, "split needs a number");
+
+    let error = parse_script("\n\nbevel 1").unwrap_err();
+    assert_eq!((error.line, error.column), (3, 1));
+
+    let error = parse_script("pull now").unwrap_err();
+    assert_eq!((error.line, error.column), (1, 6));
+
+    let error = parse_script("color #ff00").unwrap_err();
+    assert_eq!((error.line, error.column), (1, 7));
+
+    let error = parse_script("scale 1 1 1 middle").unwrap_err();
+    assert_eq!(error.to_string(), "1:13: unknown pivot \"middle\"");
+
+    let error = parse_script("subdivide 1.5").unwrap_err();
+    assert_eq!(error.message, "expected a whole number, found \"1.5\"");
+}
+
+#[test]
+fn scripts_replay_like_json() {
+    let script = "split 0.5\nface\npull\ntranslate 0 0 -1\ncolor #ff0000\n";
+    let mut from_script = CommandStack::new();
+    from_script.extend_from_script(script).unwrap();
+    let mut from_json = CommandStack::new();
+    from_json
+        .extend_from_json(&from_script.to_json().unwrap())
+        .unwrap();
+    assert_eq!(from_json.to_script(), script);
+
+    let (mut a, mut b) = (Mesh::new(), Mesh::new());
+    from_script.replay(&mut a, &mut Vec::new()).unwrap();
+    from_json.replay(&mut b, &mut Vec::new()).unwrap();
+    assert_eq!(a.positions, b.positions);
+    assert_eq!(a.indices, b.indices);
+}
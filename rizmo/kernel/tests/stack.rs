@@ -0,0 +1,245 @@
+use rizmo_kernel::{
+    Command, CommandError, CommandId, CommandStack, Document, FinishLine, Mesh, Primitive,
+    ReplayError, Space, Vec3,
+};
+
+fn ids(stack: &CommandStack) -> Vec<CommandId> {
+    stack.iter().map(|(id, _)| id).collect()
+}
+
+#[test]
+fn ids_stay_stable_when_commands_are_removed() {
+    let mut stack = CommandStack::new();
+    let first = stack.push(Command::Pull);
+    let second = stack.push(Command::Split(0.5));
+    let third = stack.push(Command::MoveFaceSelection);
+
+    assert_eq!(stack.remove(second), Some(Command::Split(0.5)));
+    let fourth = stack.push(Command::Pull);
+
+    assert_eq!(ids(&stack), vec![first, third, fourth]);
+    assert_eq!(stack.get(second), None);
+}
+
+#[test]
+fn json_round_trip_preserves_order() {
+    let mut stack = CommandStack::new();
+    stack.push(Command::Split(0.25));
+    stack.push(Command::MoveEdgeSelection);
+    stack.push(Command::Translate {
+        delta: Vec3::new(1.0, 0.0, 0.0),
+        space: Space::World,
+    });
+
+    let json = stack.to_json().unwrap();
+    let mut loaded = CommandStack::new();
+    loaded.extend_from_json(&json).unwrap();
+
+    let commands: Vec<_> = loaded.iter().map(|(_, command)| command.clone()).collect();
+    let expected: Vec<_> = stack.iter().map(|(_, command)| command.clone()).collect();
+    assert_eq!(commands, expected);
+}
+
+#[test]
+fn legacy_id_maps_load_in_id_order() {
+    let json = r#"{"10":"Pull","2":{"Split":0.5},"1":"MoveEdgeSelection"}"#;
+    let mut stack = CommandStack::new();
+    stack.extend_from_json(json).unwrap();
+
+    let commands: Vec<_> = stack.iter().map(|(_, command)| command.clone()).collect();
+    assert_eq!(
+        commands,
+        vec![
+            Command::MoveEdgeSelection,
+            Command::Split(0.5),
+            Command::Pull
+        ]
+    );
+}
+
+#[test]
+fn invalid_json_adds_nothing() {
+    let mut stack = CommandStack::new();
+    assert!(stack.extend_from_json(r#"["Pull", "Explode"]"#).is_err());
+    assert!(stack.is_empty());
+}
+
+#[test]
+fn replay_is_reproducible() {
+    let mut stack = CommandStack::new();
+    stack.push(Command::Split(0.5));
+    stack.push(Command::MoveFaceSelection);
+    stack.push(Command::Pull);
+    stack.push(Command::Translate {
+        delta: Vec3::new(0.0, 0.0, -1.0),
+        space: Space::World,
+    });
+
+    let mut mesh = Mesh::new();
+    let mut selections = Vec::new();
+    stack.replay(&mut mesh, &mut selections).unwrap();
+    let first = (mesh.positions.clone(), mesh.indices.clone());
+    stack.replay(&mut mesh, &mut selections).unwrap();
+
+    assert_eq!((mesh.positions, mesh.indices), first);
+    assert_eq!(selections.len(), 1);
+}
+
+#[test]
+fn insert_and_move_relative_to_ids() {
+    let mut stack = CommandStack::new();
+    let a = stack.push(Command::Pull);
+    let b = stack.push(Command::Pull);
+
+    let before_b = stack.insert_before(b, Command::Split(0.5)).unwrap();
+    let after_a = stack.insert_after(a, Command::MoveEdgeSelection).unwrap();
+    assert_eq!(ids(&stack), vec![a, after_a, before_b, b]);
+
+    stack.move_command(a, 10).unwrap();
+    assert_eq!(ids(&stack), vec![after_a, before_b, b, a]);
+    stack.move_command(b, 0).unwrap();
+    assert_eq!(ids(&stack), vec![b, after_a, before_b, a]);
+
+    assert_eq!(stack.insert_before(100, Command::Pull), None);
+    assert_eq!(stack.move_command(100, 0), None);
+}
+
+#[test]
+fn replay_stops_at_the_finish_line() {
+    let mut stack = CommandStack::new();
+    let split = stack.push(Command::Split(0.5));
+    stack.push(Command::Pull);
+
+    let mut mesh = Mesh::new();
+    let mut selections = Vec::new();
+
+    stack.set_finish_line(FinishLine::After(split)).unwrap();
+    stack.replay(&mut mesh, &mut selections).unwrap();
+    assert_eq!(mesh.face_count(), 6);
+
+    stack.set_finish_line(FinishLine::Start);
+    stack.replay(&mut mesh, &mut selections).unwrap();
+    assert_eq!(mesh.face_count(), 4);
+
+    stack.set_finish_line(FinishLine::End);
+    stack.replay(&mut mesh, &mut selections).unwrap();
+    assert_eq!(mesh.face_count(), 8);
+
+    assert_eq!(stack.set_finish_line(FinishLine::After(100)), None);
+    assert_eq!(stack.finish_line(), FinishLine::End);
+}
+
+#[test]
+fn new_commands_go_in_at_the_finish_line() {
+    let mut stack = CommandStack::new();
+    let a = stack.push(Command::Pull);
+    let b = stack.push(Command::Pull);
+
+    stack.set_finish_line(FinishLine::After(a)).unwrap();
+    let c = stack.insert_at_finish_line(Command::Split(0.5));
+    let d = stack.insert_at_finish_line(Command::Split(0.5));
+    assert_eq!(ids(&stack), vec![a, c, d, b]);
+    assert_eq!(stack.finish_line(), FinishLine::After(d));
+
+    stack.set_finish_line(FinishLine::Start);
+    let e = stack.insert_at_finish_line(Command::Pull);
+    assert_eq!(ids(&stack), vec![e, a, c, d, b]);
+    assert_eq!(stack.finish_line(), FinishLine::After(e));
+}
+
+#[test]
+fn removing_the_command_at_the_finish_line_moves_it_up() {
+    let mut stack = CommandStack::new();
+    let a = stack.push(Command::Pull);
+    let b = stack.push(Command::Pull);
+
+    stack.set_finish_line(FinishLine::After(b)).unwrap();
+    stack.remove(b);
+    assert_eq!(stack.finish_line(), FinishLine::After(a));
+    stack.remove(a);
+    assert_eq!(stack.finish_line(), FinishLine::Start);
+}
+
+#[test]
+fn replay_stops_at_the_failing_command() {
+    let mut stack = CommandStack::new();
+    stack.push(Command::Split(0.5));
+    let bad = stack.push(Command::Split(2.0));
+    stack.push(Command::Pull);
+
+    let mut mesh = Mesh::new();
+    let mut selections = Vec::new();
+    assert_eq!(
+        stack.replay(&mut mesh, &mut selections),
+        Err(ReplayError {
+            command_id: bad,
+            error: CommandError::SplitAmountOutOfRange(2.0),
+        })
+    );
+    assert_eq!(mesh.face_count(), 6);
+}
+
+#[test]
+fn replay_starts_from_the_base_mesh() {
+    // An octahedron
+    let positions = [
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(-1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, -1.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+        Vec3::new(0.0, 0.0, -1.0),
+    ];
+    let triangles = [
+        [0, 4, 2],
+        [2, 4, 1],
+        [1, 4, 3],
+        [3, 4, 0],
+        [0, 2, 5],
+        [2, 1, 5],
+        [1, 3, 5],
+        [3, 0, 5],
+    ];
+    let base = Mesh::from_triangles(&positions, &[], &triangles).unwrap();
+
+    let mut stack = CommandStack::new();
+    stack.set_base(Some(base));
+    stack.push(Command::Split(0.5));
+
+    let mut mesh = Mesh::new();
+    stack.replay(&mut mesh, &mut Vec::new()).unwrap();
+    assert_eq!(mesh.face_count(), 10);
+    assert_eq!(mesh.vertex_count(), 7);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn replays_start_from_the_primitive() {
+    let mut stack = CommandStack::new();
+    stack.set_base(Some(Primitive::Cube.to_mesh()));
+    stack.set_primitive(Primitive::Icosphere { subdivisions: 1 });
+    stack.push(Command::Split(0.5));
+
+    let mut mesh = Mesh::new();
+    stack.replay(&mut mesh, &mut Vec::new()).unwrap();
+    assert!(stack.base().is_none());
+    assert_eq!(mesh.face_count(), 82);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn documents_without_a_base_drop_the_current_one() {
+    let mut stack = CommandStack::new();
+    stack.set_base(Some(Primitive::Cube.to_mesh()));
+    stack.extend_from_document(Document {
+        primitive: Primitive::Icosphere { subdivisions: 1 },
+        commands: vec![Command::Split(0.5)],
+        ..Document::default()
+    });
+
+    let mut mesh = Mesh::new();
+    stack.replay(&mut mesh, &mut Vec::new()).unwrap();
+    assert!(stack.base().is_none());
+    assert_eq!(mesh.face_count(), 82);
+    assert!(mesh.validate().is_valid());
+}
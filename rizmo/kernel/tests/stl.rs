@@ -0,0 +1,96 @@
+use rizmo_kernel::io::stl::{WatertightIssue, check_watertight, write_stl_ascii, write_stl_binary};
+use rizmo_kernel::{Command, CommandStack, Mesh, Space, Vec3};
+
+#[test]
+fn replayed_solids_are_watertight() {
+    let mut stack = CommandStack::new();
+    stack.push(Command::Split(0.5));
+    stack.push(Command::MoveFaceSelection);
+    stack.push(Command::Pull);
+    stack.push(Command::Translate {
+        delta: Vec3::new(0.0, 0.0, -1.0),
+        space: Space::World,
+    });
+
+    let mut mesh = Mesh::new();
+    assert!(check_watertight(&mesh).is_watertight());
+    stack.replay(&mut mesh, &mut Vec::new()).unwrap();
+    assert!(check_watertight(&mesh).is_watertight());
+}
+
+#[test]
+fn open_edges_are_reported() {
+    let mut mesh = Mesh::new();
+    mesh.connections[0] = 9;
+
+    let issues = check_watertight(&mesh).issues;
+    assert!(issues.contains(&WatertightIssue::OpenEdge { meta_index: 0 }));
+}
+
+#[test]
+fn flipped_faces_are_reported() {
+    let mut mesh = Mesh::new();
+    // Reverse the winding of the first face while keeping its twins
+    mesh.indices[1..3].swap(0, 1);
+    mesh.connections.swap(0, 2);
+    mesh.connections[8] = 2;
+    mesh.connections[3] = 0;
+
+    assert_eq!(
+        check_watertight(&mesh).issues,
+        vec![WatertightIssue::InvertedFace { face: 0 }]
+    );
+}
+
+#[test]
+fn inside_out_solids_are_reported() {
+    let mut mesh = Mesh::new();
+    // Mirroring the tetrahedron turns every face inwards
+    for position in mesh.positions.iter_mut() {
+        position.x = -position.x;
+    }
+
+    assert_eq!(check_watertight(&mesh).issues.len(), 4);
+}
+
+#[test]
+fn non_manifold_edges_are_reported() {
+    let mut mesh = Mesh::new();
+    // A second tetrahedron sharing the edge between vertices 1 and 2
+    let apex = mesh.add_vertex(Vec3::new(-1.0, 1.0, 1.0)) as i32;
+    let start = mesh.indices.len() as i32;
+    mesh.add_faces(
+        [1, 2, apex, 2, 1, apex],
+        [start + 3, start + 5, start + 4, start, start + 2, start + 1],
+    );
+
+    assert!(
+        check_watertight(&mesh)
+            .issues
+            .contains(&WatertightIssue::NonManifoldEdge { from: 1, to: 2 })
+    );
+}
+
+#[test]
+fn binary_stl_has_a_record_per_face() {
+    let mut stl = Vec::new();
+    write_stl_binary(&Mesh::new(), &mut stl).unwrap();
+
+    assert_eq!(stl.len(), 84 + 4 * 50);
+    assert_eq!(u32::from_le_bytes(stl[80..84].try_into().unwrap()), 4);
+    // The first face is on the x = 0 plane and faces -x
+    let normal_x = f32::from_le_bytes(stl[84..88].try_into().unwrap());
+    assert_eq!(normal_x, -1.0);
+}
+
+#[test]
+fn ascii_stl_has_a_facet_per_face() {
+    let mut stl = Vec::new();
+    write_stl_ascii(&Mesh::new(), &mut stl).unwrap();
+    let stl = String::from_utf8(stl).unwrap();
+
+    assert!(stl.starts_with("solid gizmo\n"));
+    assert!(stl.trim_end().ends_with("endsolid gizmo"));
+    assert_eq!(stl.matches("facet normal").count(), 4);
+    assert!(stl.contains("facet normal -1 0 0"));
+}
@@ -0,0 +1,126 @@
+use rizmo_kernel::{Color, Command, CommandError, CommandStack, Mesh, Space, TopologyIssue, Vec3};
+
+#[test]
+fn commands_keep_the_mesh_valid() {
+    let mut stack = CommandStack::new();
+    stack.set_validation(true);
+    stack.push(Command::Split(0.5));
+    stack.push(Command::MoveFaceSelection);
+    stack.push(Command::Pull);
+    stack.push(Command::Translate {
+        delta: Vec3::new(0.0, 0.0, -1.0),
+        space: Space::World,
+    });
+    stack.push(Command::MoveEdgeSelection);
+    stack.push(Command::Split(0.3));
+
+    let mut mesh = Mesh::new();
+    assert!(mesh.validate().is_valid());
+    stack.replay(&mut mesh, &mut Vec::new()).unwrap();
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn asymmetric_twins_are_reported() {
+    let mut mesh = Mesh::new();
+    mesh.connections[0] = 9;
+
+    let report = mesh.validate();
+    assert!(report.issues.contains(&TopologyIssue::AsymmetricTwin {
+        meta_index: 0,
+        twin: 9
+    }));
+}
+
+#[test]
+fn flipped_faces_are_reported() {
+    let mut mesh = Mesh::new();
+    // Reverse the winding of the first face while keeping its twins
+    mesh.indices[1..3].swap(0, 1);
+    mesh.connections.swap(0, 2);
+    mesh.connections[8] = 2;
+    mesh.connections[3] = 0;
+
+    let report = mesh.validate();
+    assert!(
+        report
+            .issues
+            .contains(&TopologyIssue::InconsistentOrientation {
+                meta_index: 0,
+                twin: 3
+            })
+    );
+}
+
+#[test]
+fn out_of_range_indices_stop_the_report_early() {
+    let mut mesh = Mesh::new();
+    mesh.indices[4] = 7;
+    mesh.connections[5] = 12;
+
+    let report = mesh.validate();
+    assert_eq!(
+        report.issues,
+        vec![
+            TopologyIssue::VertexOutOfRange {
+                meta_index: 4,
+                vertex: 7
+            },
+            TopologyIssue::ConnectionOutOfRange {
+                meta_index: 5,
+                connection: 12
+            },
+        ]
+    );
+}
+
+#[test]
+fn unused_vertices_are_reported() {
+    let mut mesh = Mesh::new();
+    mesh.add_vertex(Vec3::ZERO);
+
+    assert_eq!(
+        mesh.validate().issues,
+        vec![TopologyIssue::UnusedVertex { vertex: 4 }]
+    );
+}
+
+#[test]
+fn clean_moves_the_last_vertex_into_a_deleted_slot() {
+    let mut mesh = Mesh::new();
+    // Replace vertex 1 with a red copy at the end and delete it
+    let copy = mesh.add_vertex(mesh.positions[1]);
+    mesh.colors[copy] = Color::from_rgb(1.0, 0.0, 0.0);
+    for index in mesh.indices.iter_mut().filter(|index| **index == 1) {
+        *index = copy as i32;
+    }
+    mesh.delete_vertex(1);
+
+    mesh.clean();
+    assert_eq!(mesh.vertex_count(), 4);
+    assert!(mesh.deleted_vertices().is_empty());
+    assert_eq!(mesh.positions.len(), Mesh::BLOCK_SIZE);
+    assert_eq!(mesh.colors.len(), Mesh::BLOCK_SIZE);
+    assert_eq!(mesh.positions[1], Vec3::new(0.0, 0.0, 1.0));
+    assert_eq!(mesh.colors[1], Color::from_rgb(1.0, 0.0, 0.0));
+    assert_eq!(mesh.indices, Mesh::new().indices);
+    assert!(mesh.validate().is_valid());
+}
+
+#[test]
+fn replay_stops_before_the_command_that_breaks_the_mesh() {
+    // A vertex no face uses makes every command after it fail validation
+    let mut base = Mesh::new();
+    base.add_vertex(Vec3::ZERO);
+    let mut stack = CommandStack::new();
+    stack.set_base(Some(base));
+    stack.set_validation(true);
+    let split = stack.push(Command::Split(0.5));
+
+    let mut mesh = Mesh::new();
+    let error = stack.replay(&mut mesh, &mut Vec::new()).unwrap_err();
+    assert_eq!(error.command_id, split);
+    assert!(matches!(error.error, CommandError::InvalidTopology(_)));
+    assert_eq!(mesh.face_count(), 4);
+    assert_eq!(mesh.vertex_count(), 5);
+}
@@ -0,0 +1,260 @@
+// Replays saved command stacks without Godot and writes the resulting meshes to disk.
+//
+// Usage: rizmo-replay [--validate] <commands.json|commands.gizmo> <output>
+//        rizmo-replay [--validate] [--format <extension>] <input directory> <output directory>
+//
+// Stacks are read as JSON documents, or as scripts when their extension is .gizmo. The output
+// format is picked from the output file's extension. When given directories, every .json and
+// .gizmo file in the input directory is replayed and written to the output directory with the
+// same name, in the format given by --format (glb unless told otherwise). JSON output can't go
+// back into the input directory, since it would overwrite the stacks being read, and two stacks
+// that differ only by extension (a.json and a.gizmo) can't both write a.<format>.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use rizmo_kernel::io::gltf::{write_glb, write_gltf};
+use rizmo_kernel::io::json::write_json;
+use rizmo_kernel::io::obj::{write_mtl, write_obj};
+use rizmo_kernel::io::ply::{PlyFormat, write_ply};
+use rizmo_kernel::io::stl::{check_watertight, write_stl_binary};
+use rizmo_kernel::{CommandStack, Mesh};
+
+const USAGE: &str = "usage: rizmo-replay [--validate] <commands.json|commands.gizmo> <output>
+       rizmo-replay [--validate] [--format <extension>] <input directory> <output directory>";
+
+// Exit codes
+const INVALID_ARGUMENTS: u8 = 1;
+const INVALID_STACK: u8 = 2;
+const COMMAND_FAILED: u8 = 3;
+const WRITE_FAILED: u8 = 4;
+
+fn main() -> ExitCode {
+    let mut validate = false;
+    let mut format = None;
+    let mut paths = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--validate" => validate = true,
+            "--format" => match args.next().as_deref().and_then(MeshFormat::from_extension) {
+                Some(f) => format = Some(f),
+                None => {
+                    eprintln!(
+                        "rizmo-replay: --format needs one of json, obj, gltf, glb, stl or ply"
+                    );
+                    return ExitCode::from(INVALID_ARGUMENTS);
+                }
+            },
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let [input, output] = paths.as_slice() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(INVALID_ARGUMENTS);
+    };
+
+    let result = if input.is_dir() {
+        replay_directory(input, output, format.unwrap_or(MeshFormat::Glb), validate)
+    } else {
+        let Some(format) = format.or_else(|| MeshFormat::from_path(output)) else {
+            eprintln!("rizmo-replay: don't know how to write {}", output.display());
+            return ExitCode::from(INVALID_ARGUMENTS);
+        };
+        if same_path(input, output) {
+            eprintln!(
+                "rizmo-replay: writing {} would overwrite the stack itself",
+                output.display()
+            );
+            return ExitCode::from(INVALID_ARGUMENTS);
+        }
+        replay_file(input, output, format, validate)
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(code) => ExitCode::from(code),
+    }
+}
+
+// Replays every stack in the directory, carrying on past failures so one broken stack doesn't
+// hide the others. Fails with the exit code of the first stack that failed.
+fn replay_directory(
+    input: &Path,
+    output: &Path,
+    format: MeshFormat,
+    validate: bool,
+) -> Result<(), u8> {
+    if format == MeshFormat::Json && same_path(input, output) {
+        eprintln!(
+            "rizmo-replay: writing JSON into {} would overwrite the stacks in it",
+            input.display()
+        );
+        return Err(INVALID_ARGUMENTS);
+    }
+    let entries = fs::read_dir(input).map_err(|error| {
+        eprintln!("rizmo-replay: couldn't read {}: {error}", input.display());
+        INVALID_ARGUMENTS
+    })?;
+    let mut stacks: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.is_file()
+                && (is_script(path) || MeshFormat::from_path(path) == Some(MeshFormat::Json))
+        })
+        .collect();
+    stacks.sort();
+
+    fs::create_dir_all(output).map_err(|error| {
+        eprintln!(
+            "rizmo-replay: couldn't create {}: {error}",
+            output.display()
+        );
+        WRITE_FAILED
+    })?;
+
+    // a.json and a.gizmo would both write a.<ext>, so the second one is refused
+    let mut written = HashSet::new();
+    let mut result = Ok(());
+    for stack in stacks {
+        let mesh_path = output
+            .join(stack.file_name().unwrap())
+            .with_extension(format.extension());
+        let stack_result = if written.insert(mesh_path.clone()) {
+            replay_file(&stack, &mesh_path, format, validate)
+        } else {
+            eprintln!(
+                "rizmo-replay: skipping {}, another stack already wrote {}",
+                stack.display(),
+                mesh_path.display()
+            );
+            Err(INVALID_ARGUMENTS)
+        };
+        if result.is_ok() {
+            result = stack_result;
+        }
+    }
+    result
+}
+
+// True when both paths exist and lead to the same file or directory
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn is_script(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gizmo"))
+}
+
+fn replay_file(input: &Path, output: &Path, format: MeshFormat, validate: bool) -> Result<(), u8> {
+    let contents = fs::read_to_string(input).map_err(|error| {
+        eprintln!("rizmo-replay: couldn't read {}: {error}", input.display());
+        INVALID_STACK
+    })?;
+    let mut commands = CommandStack::new();
+    let loaded = match is_script(input) {
+        true => commands
+            .extend_from_script(&contents)
+            .map_err(|error| error.to_string()),
+        false => commands
+            .extend_from_json(&contents)
+            .map_err(|error| error.to_string()),
+    };
+    if let Err(error) = loaded {
+        eprintln!(
+            "rizmo-replay: {} isn't a valid command stack: {error}",
+            input.display()
+        );
+        return Err(INVALID_STACK);
+    }
+    commands.set_validation(validate);
+
+    let mut mesh = Mesh::new();
+    if let Err(error) = commands.replay(&mut mesh, &mut Vec::new()) {
+        eprintln!("rizmo-replay: {}: {error}", input.display());
+        return Err(COMMAND_FAILED);
+    }
+
+    // Printers need a closed solid, but the STL is still worth having to look at
+    if format == MeshFormat::Stl {
+        let report = check_watertight(&mesh);
+        if !report.is_watertight() {
+            eprintln!(
+                "rizmo-replay: warning: {} won't print cleanly: {report}",
+                input.display()
+            );
+        }
+    }
+
+    format.write(&mesh, output).map_err(|error| {
+        eprintln!("rizmo-replay: couldn't write {}: {error}", output.display());
+        WRITE_FAILED
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MeshFormat {
+    Json,
+    Obj,
+    Gltf,
+    Glb,
+    Stl,
+    Ply,
+}
+
+impl MeshFormat {
+    fn from_path(path: &Path) -> Option<MeshFormat> {
+        MeshFormat::from_extension(path.extension()?.to_str()?)
+    }
+
+    fn from_extension(extension: &str) -> Option<MeshFormat> {
+        match extension.to_ascii_lowercase().as_str() {
+            "json" => Some(MeshFormat::Json),
+            "obj" => Some(MeshFormat::Obj),
+            "gltf" => Some(MeshFormat::Gltf),
+            "glb" => Some(MeshFormat::Glb),
+            "stl" => Some(MeshFormat::Stl),
+            "ply" => Some(MeshFormat::Ply),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            MeshFormat::Json => "json",
+            MeshFormat::Obj => "obj",
+            MeshFormat::Gltf => "gltf",
+            MeshFormat::Glb => "glb",
+            MeshFormat::Stl => "stl",
+            MeshFormat::Ply => "ply",
+        }
+    }
+
+    fn write(&self, mesh: &Mesh, path: &Path) -> std::io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        match self {
+            MeshFormat::Json => write_json(mesh, writer),
+            MeshFormat::Obj => {
+                // The material library goes next to the OBJ
+                let mtl_path = path.with_extension("mtl");
+                let material_library = mtl_path.file_name().unwrap().to_string_lossy();
+                write_obj(mesh, writer, Some(&material_library))?;
+                write_mtl(BufWriter::new(File::create(&mtl_path)?))
+            }
+            MeshFormat::Gltf => write_gltf(mesh, writer),
+            MeshFormat::Glb => write_glb(mesh, writer),
+            MeshFormat::Stl => write_stl_binary(mesh, writer),
+            MeshFormat::Ply => write_ply(mesh, writer, PlyFormat::BinaryLittleEndian),
+        }
+    }
+}
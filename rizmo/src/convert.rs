@@ -0,0 +1,20 @@
+use godot::prelude::*;
+use rizmo_kernel::math;
+
+// The kernel has its own vector and color types, these convert at the Godot boundary.
+
+pub fn to_vector3(v: math::Vec3) -> Vector3 {
+    Vector3::new(v.x, v.y, v.z)
+}
+
+pub fn to_vec3(v: Vector3) -> math::Vec3 {
+    math::Vec3::new(v.x, v.y, v.z)
+}
+
+pub fn to_godot_color(c: math::Color) -> Color {
+    Color::from_rgba(c.r, c.g, c.b, c.a)
+}
+
+pub fn to_kernel_color(c: Color) -> math::Color {
+    math::Color::from_rgba(c.r, c.g, c.b, c.a)
+}
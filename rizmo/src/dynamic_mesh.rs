@@ -1,43 +1,40 @@
-use std::collections::HashMap;
-
-use godot::classes::StandardMaterial3D;
+use godot::classes::file_access::ModeFlags;
+use godot::classes::mesh::{ArrayType, PrimitiveType as SurfacePrimitive};
+use godot::classes::{ArrayMesh, FileAccess, StandardMaterial3D};
 use godot::classes::{RenderingServer, base_material_3d::Flags, rendering_server::PrimitiveType};
 use godot::prelude::*;
+use rizmo_kernel::Mesh;
+use rizmo_kernel::io::build_mesh;
+use rizmo_kernel::io::gltf::{write_glb, write_gltf};
+use rizmo_kernel::io::obj::{write_mtl, write_obj};
+use rizmo_kernel::io::ply::{PlyFormat, write_ply};
+use rizmo_kernel::io::stl::{check_watertight, write_stl_ascii, write_stl_binary};
+
+use crate::convert::{to_godot_color, to_kernel_color, to_vec3, to_vector3};
 
-pub type MetaIndexId = i32;
+pub use rizmo_kernel::MetaIndexId;
 
 // The idea is to maximize performance by leveraging Godot's RenderingServer
 // and minimizing memory allocations.
 //
 // DynamicMesh works by overestimated the resources needed. It allocates a bunch of memory upfront,
 // gives it to Godot, and then subsequent updates to the mesh are done through RenderingServer.mesh_surface_update_*_region.
+//
+// The geometry itself lives in a rizmo_kernel::Mesh, this node only takes care of getting it on screen.
 #[derive(GodotClass)]
 #[class(init, base=Node3D)]
 pub struct DynamicMesh {
-    #[var]
-    pub positions: PackedVector3Array,
-    #[var]
-    pub colors: PackedColorArray,
-    deleted_vertices: Vec<usize>,
-    #[var]
-    pub indices: PackedInt32Array,
-    #[var]
-    pub connections: PackedInt32Array,
+    pub geometry: Mesh,
     mesh_rid: Option<Rid>,
     instance_rid: Option<Rid>,
     #[init(val = StandardMaterial3D::new_gd())]
     material: Gd<StandardMaterial3D>,
-    index: usize,
-    pub tracked_indices: HashMap<MetaIndexId, i32>,
-    last_meta_index_id: MetaIndexId,
     base: Base<Node3D>,
 }
 
 #[godot_api]
 impl INode3D for DynamicMesh {
     fn ready(&mut self) {
-        self.clear();
-
         let mut rs = RenderingServer::singleton();
         let mesh_rid = rs.mesh_create();
         let instance_rid = rs.instance_create();
@@ -59,107 +56,143 @@ impl INode3D for DynamicMesh {
 
 #[godot_api]
 pub impl DynamicMesh {
-    pub const BLOCK_SIZE: usize = 64;
-
     #[func]
     pub fn clear(&mut self) {
-        self.positions.resize(DynamicMesh::BLOCK_SIZE);
-        self.positions.as_mut_slice()[0..4].copy_from_slice(&[
-            Vector3::new(0.0, 0.0, 0.0),
-            Vector3::new(0.0, 0.0, 1.0),
-            Vector3::new(0.0, 1.0, 0.0),
-            Vector3::new(1.0, 0.0, 0.0),
-        ]);
-        self.colors.resize(DynamicMesh::BLOCK_SIZE);
-
-        self.indices = PackedInt32Array::from(&[0, 2, 1, 0, 1, 3, 0, 3, 2, 3, 1, 2]);
-        self.connections = PackedInt32Array::from(&[8, 10, 3, 2, 9, 6, 5, 11, 0, 4, 1, 7]);
-        self.index = 4;
-        self.tracked_indices.clear();
-        self.last_meta_index_id = 0;
+        self.geometry.clear();
     }
 
-    // TODO: Test to make sure this works
-    pub fn clean(&mut self) {
-        if self.deleted_vertices.is_empty() {
-            return;
+    /// Builds a DynamicMesh from a surface of any Godot mesh, reading its vertices, colors and
+    /// indices. Vertices split apart for normals or UVs are merged back together by position, and
+    /// open edges are left as holes. Returns null if it can't be edited.
+    #[func]
+    pub fn from_mesh(mesh: Gd<godot::classes::Mesh>, surface: i32) -> Option<Gd<DynamicMesh>> {
+        if surface < 0 || surface >= mesh.get_surface_count() {
+            godot_error!("The mesh has no surface {surface}");
+            return None;
         }
-        self.deleted_vertices.sort();
-        let mut deleted_start_index = 0;
-        let mut deleted_end_index = self.deleted_vertices.len() - 1;
-        let mut used_end = self.index - 1;
-
-        while used_end == self.deleted_vertices[deleted_end_index] {
-            if deleted_end_index == 0 {
-                break;
+        if let Ok(array_mesh) = mesh.clone().try_cast::<ArrayMesh>() {
+            let primitive = array_mesh.surface_get_primitive_type(surface);
+            if primitive != SurfacePrimitive::TRIANGLES {
+                godot_error!("Surface {surface} is made of {primitive:?}, not triangles");
+                return None;
             }
-
-            used_end -= 1;
-            deleted_end_index -= 1;
         }
 
-        while used_end > self.deleted_vertices[deleted_start_index] {
-            // Move it to the first deleted element
-            self.positions[self.deleted_vertices[deleted_start_index]] = self.positions[used_end];
+        let arrays = mesh.surface_get_arrays(surface);
+        let array = |array_type: ArrayType| arrays.get(array_type.ord() as usize);
+        let positions: Vec<_> = array(ArrayType::VERTEX)
+            .and_then(|positions| positions.try_to::<PackedVector3Array>().ok())
+            .unwrap_or_default()
+            .as_slice()
+            .iter()
+            .copied()
+            .map(to_vec3)
+            .collect();
+        let colors: Vec<_> = array(ArrayType::COLOR)
+            .and_then(|colors| colors.try_to::<PackedColorArray>().ok())
+            .unwrap_or_default()
+            .as_slice()
+            .iter()
+            .copied()
+            .map(to_kernel_color)
+            .collect();
+        let indices: Vec<usize> = match array(ArrayType::INDEX)
+            .and_then(|indices| indices.try_to::<PackedInt32Array>().ok())
+        {
+            Some(indices) if !indices.is_empty() => indices
+                .as_slice()
+                .iter()
+                .map(|&index| index as usize)
+                .collect(),
+            // Surfaces without indices list every corner in order
+            _ => (0..positions.len()).collect(),
+        };
 
-            // Update indices
-            for i in 0..self.indices.len() {
-                if self.indices[i] == used_end as i32 {
-                    self.indices[i] = self.deleted_vertices[deleted_start_index] as i32;
+        // Godot winds faces clockwise, build_mesh expects them counter-clockwise
+        let polygons: Vec<Vec<usize>> = indices
+            .chunks_exact(3)
+            .map(|face| vec![face[0], face[2], face[1]])
+            .collect();
+        match build_mesh(&positions, &colors, &polygons, true) {
+            Ok((geometry, report)) => {
+                if !report.is_clean() {
+                    godot_warn!("Repaired surface {surface}: {report}");
                 }
+                let mut dynamic_mesh = DynamicMesh::new_alloc();
+                dynamic_mesh.bind_mut().geometry = geometry;
+                Some(dynamic_mesh)
             }
-
-            // Move the starting index forward
-            deleted_start_index += 1;
-
-            // Find the final element that isn't deleted
-            while used_end == self.deleted_vertices[deleted_end_index] {
-                if deleted_end_index == 0 {
-                    break;
-                }
-                used_end -= 1;
-                deleted_end_index -= 1;
+            Err(error) => {
+                godot_error!("Surface {surface} can't be edited: {error}");
+                None
             }
         }
-
-        self.index = used_end;
-        self.deleted_vertices.clear();
-        let new_size = (self.index as f32 / DynamicMesh::BLOCK_SIZE as f32).ceil() as usize
-            * DynamicMesh::BLOCK_SIZE;
-        self.positions.resize(new_size);
     }
 
-    pub fn request_more_memory(&mut self) {
-        let new_size = self.positions.len() + DynamicMesh::BLOCK_SIZE;
-        self.positions.resize(new_size);
-        self.colors.resize(new_size);
+    /// Bakes the current geometry into a standalone ArrayMesh that can be saved as a resource.
+    #[func]
+    pub fn to_array_mesh(&self) -> Gd<ArrayMesh> {
+        let live = self.geometry.vertex_count();
+        let positions: PackedVector3Array = self.geometry.positions[..live]
+            .iter()
+            .copied()
+            .map(to_vector3)
+            .collect();
+        let colors: PackedColorArray = self.geometry.colors[..live]
+            .iter()
+            .copied()
+            .map(to_godot_color)
+            .collect();
+        let mut surface = VariantArray::new();
+        surface.resize(ArrayType::MAX.ord() as usize, &Variant::nil());
+        surface.set(ArrayType::VERTEX.ord() as usize, &positions.to_variant());
+        surface.set(ArrayType::COLOR.ord() as usize, &colors.to_variant());
+        surface.set(
+            ArrayType::INDEX.ord() as usize,
+            &self.get_indices().to_variant(),
+        );
+
+        let mut array_mesh = ArrayMesh::new_gd();
+        array_mesh.add_surface_from_arrays(SurfacePrimitive::TRIANGLES, &surface);
+        let mut material = StandardMaterial3D::new_gd();
+        material.set_flag(Flags::ALBEDO_FROM_VERTEX_COLOR, true);
+        array_mesh.surface_set_material(0, &material);
+        array_mesh
     }
 
-    pub fn add_vertex(&mut self, position: Vector3) -> usize {
-        if self.index % DynamicMesh::BLOCK_SIZE == 0 {
-            self.request_more_memory();
-        }
+    #[func]
+    pub fn get_positions(&self) -> PackedVector3Array {
+        self.geometry
+            .positions
+            .iter()
+            .copied()
+            .map(to_vector3)
+            .collect()
+    }
 
-        let index = self.index;
-        self.positions[index] = position;
-        self.index += 1;
-        return index;
+    #[func]
+    pub fn get_colors(&self) -> PackedColorArray {
+        self.geometry
+            .colors
+            .iter()
+            .copied()
+            .map(to_godot_color)
+            .collect()
     }
 
     #[func]
-    pub fn add_faces(&mut self, indices: [i32; 6], connections: [i32; 6]) {
-        self.indices.extend_array(&PackedInt32Array::from(&indices));
+    pub fn get_indices(&self) -> PackedInt32Array {
+        PackedInt32Array::from(self.geometry.indices.as_slice())
+    }
 
-        let connections_length = self.connections.len();
-        self.connections
-            .extend_array(&PackedInt32Array::from(&connections));
+    #[func]
+    pub fn get_connections(&self) -> PackedInt32Array {
+        PackedInt32Array::from(self.geometry.connections.as_slice())
+    }
 
-        for (offset, connection) in connections.iter().enumerate() {
-            if *connection > (connections_length - 1) as i32 {
-                continue;
-            }
-            self.connections[*connection as usize] = (connections_length + offset) as i32;
-        }
+    #[func]
+    pub fn add_faces(&mut self, indices: [i32; 6], connections: [i32; 6]) {
+        self.geometry.add_faces(indices, connections);
     }
 
     #[func]
@@ -167,10 +200,10 @@ pub impl DynamicMesh {
         let mut rs = RenderingServer::singleton();
         rs.mesh_clear(self.mesh_rid.unwrap());
         let surface = varray!(
-            self.positions.clone(), // Positions (Vector3)
+            self.get_positions(), // Positions (Vector3)
             Variant::nil(),
             Variant::nil(),
-            self.colors.clone(),
+            self.get_colors(),
             Variant::nil(),
             Variant::nil(),
             Variant::nil(),
@@ -179,7 +212,7 @@ pub impl DynamicMesh {
             Variant::nil(),
             Variant::nil(),
             Variant::nil(),
-            self.indices.clone() // Indices (Vector3)
+            self.get_indices() // Indices (Vector3)
         );
         rs.mesh_add_surface_from_arrays(self.mesh_rid.unwrap(), PrimitiveType::TRIANGLES, &surface);
         rs.mesh_surface_set_material(self.mesh_rid.unwrap(), 0, self.material.get_rid());
@@ -189,76 +222,143 @@ pub impl DynamicMesh {
     pub fn submit_updated_positions(&self, index: i32, size: i32) {
         let i: usize = index.try_into().unwrap();
         let s: usize = size.try_into().unwrap();
-        let positions_as_bytes = self.positions.subarray(i, i + s).to_byte_array();
+        let positions: PackedVector3Array = self.geometry.positions[i..i + s]
+            .iter()
+            .copied()
+            .map(to_vector3)
+            .collect();
 
         let mut rs = RenderingServer::singleton();
         rs.mesh_surface_update_vertex_region(
             self.mesh_rid.unwrap(),
             0,
             3 * 4 * index,
-            &positions_as_bytes,
+            &positions.to_byte_array(),
         );
     }
 
+    /// Returns -1 if `meta_index` isn't a corner of the mesh.
     #[func]
     pub fn track_index(&mut self, meta_index: i32) -> MetaIndexId {
-        let new_meta_index_id = self.last_meta_index_id;
-        self.last_meta_index_id += 1;
+        self.geometry
+            .track_index(meta_index)
+            .unwrap_or_else(|error| {
+                godot_error!("{error}");
+                -1
+            })
+    }
 
-        if meta_index > (self.indices.len() - 1).try_into().unwrap() {
-            panic!("meta_index can't be larger than the number of indices.");
+    #[func]
+    pub fn traverse_connection(&mut self, meta_index_id: MetaIndexId) {
+        if let Err(error) = self.geometry.traverse_connection(meta_index_id) {
+            godot_error!("{error}");
         }
+    }
 
-        self.tracked_indices.insert(new_meta_index_id, meta_index);
+    /// Returns -1 if `meta_index_id` isn't tracked.
+    #[func]
+    pub fn get_meta_index(&self, meta_index_id: MetaIndexId) -> i32 {
+        self.geometry
+            .get_meta_index(meta_index_id)
+            .unwrap_or_else(|error| {
+                godot_error!("{error}");
+                -1
+            })
+    }
 
-        new_meta_index_id
+    #[func]
+    fn get_face_positions(&self, meta_index_id: MetaIndexId) -> PackedVector3Array {
+        match self.geometry.get_face_positions(meta_index_id) {
+            Ok(positions) => positions.map(to_vector3).into_iter().collect(),
+            Err(error) => {
+                godot_error!("{error}");
+                PackedVector3Array::new()
+            }
+        }
     }
 
+    /// Writes the mesh to `path` as a Wavefront OBJ, with its material library next to it.
     #[func]
-    pub fn traverse_connection(&mut self, meta_index_id: MetaIndexId) {
-        self.tracked_indices.insert(
-            meta_index_id,
-            self.connections[self.tracked_indices[&meta_index_id].try_into().unwrap()],
-        );
+    pub fn export_obj(&self, path: GString) -> bool {
+        let mtl_path = GString::from(format!("{}.mtl", path.get_basename()));
+        let mut obj = Vec::new();
+        let mut mtl = Vec::new();
+        let material_library = mtl_path.get_file().to_string();
+        write_obj(&self.geometry, &mut obj, Some(&material_library)).unwrap();
+        write_mtl(&mut mtl).unwrap();
+        save_file(&path, &obj) && save_file(&mtl_path, &mtl)
     }
 
+    /// Writes the mesh to `path` as glTF 2.0 with the buffer embedded in the file.
     #[func]
-    pub fn get_meta_index(&self, meta_index_id: MetaIndexId) -> i32 {
-        *self.tracked_indices.get(&meta_index_id).unwrap()
+    pub fn export_gltf(&self, path: GString) -> bool {
+        let mut gltf = Vec::new();
+        write_gltf(&self.geometry, &mut gltf).unwrap();
+        save_file(&path, &gltf)
     }
 
+    /// Writes the mesh to `path` as binary glTF 2.0.
     #[func]
-    fn get_face_positions(&self, meta_index_id: MetaIndexId) -> PackedVector3Array {
-        let (start, offset) = decompose_meta_index(self.get_meta_index(meta_index_id) as usize);
-        let mut indices = self.indices.subarray(start, start + 3);
-        let indices_rotated = indices.as_mut_slice();
-        indices_rotated.rotate_left(offset);
-        PackedVector3Array::from([
-            self.positions[indices_rotated[0] as usize],
-            self.positions[indices_rotated[1] as usize],
-            self.positions[indices_rotated[2] as usize],
-        ])
+    pub fn export_glb(&self, path: GString) -> bool {
+        let mut glb = Vec::new();
+        write_glb(&self.geometry, &mut glb).unwrap();
+        save_file(&path, &glb)
     }
 
+    /// Writes the mesh to `path` as STL, binary unless `ascii` is set. Run check_watertight first
+    /// to find out whether it will print.
     #[func]
-    pub fn modify_vertex(&mut self, meta_index_id: MetaIndexId, position: Vector3) {
-        // It's not enough to update a single vertex
-        // Some vertices are "tied," they have the same position but different attributes
-        // This algorithm navigates all tied vertices and updates them.
-        // Traverse the shape until we arrive where we started.
-
-        let starting_meta_index = *self.tracked_indices.get(&meta_index_id).unwrap();
-        let mut next_meta_index = starting_meta_index;
-        let mut update_next_vertex = || {
-            self.positions[self.indices[next_meta_index as usize] as usize] = position;
-            next_meta_index = self.connections[next_meta_index as usize];
-            let (start, offset) = decompose_meta_index(next_meta_index as usize);
-            next_meta_index = (start + (offset + 1) % 3) as i32;
-            return next_meta_index;
+    pub fn export_stl(&self, path: GString, ascii: bool) -> bool {
+        let mut stl = Vec::new();
+        if ascii {
+            write_stl_ascii(&self.geometry, &mut stl).unwrap();
+        } else {
+            write_stl_binary(&self.geometry, &mut stl).unwrap();
+        }
+        save_file(&path, &stl)
+    }
+
+    /// Writes the mesh to `path` as PLY with vertex colors, binary little-endian unless `binary`
+    /// is false.
+    #[func]
+    pub fn export_ply(&self, path: GString, binary: bool) -> bool {
+        let format = match binary {
+            true => PlyFormat::BinaryLittleEndian,
+            false => PlyFormat::Ascii,
         };
-        let mut next = update_next_vertex();
-        while next != starting_meta_index {
-            next = update_next_vertex();
+        let mut ply = Vec::new();
+        write_ply(&self.geometry, &mut ply, format).unwrap();
+        save_file(&path, &ply)
+    }
+
+    /// Describes everything that stops the mesh from being a closed solid, empty if it is one.
+    #[func]
+    pub fn check_watertight(&self) -> PackedStringArray {
+        check_watertight(&self.geometry)
+            .issues
+            .iter()
+            .map(|issue| GString::from(issue.to_string()))
+            .collect()
+    }
+
+    /// Describes everything wrong with the topology, empty if there's nothing.
+    #[func]
+    pub fn validate(&self) -> PackedStringArray {
+        self.geometry
+            .validate()
+            .issues
+            .iter()
+            .map(|issue| GString::from(issue.to_string()))
+            .collect()
+    }
+
+    #[func]
+    pub fn modify_vertex(&mut self, meta_index_id: MetaIndexId, position: Vector3) {
+        if let Err(error) = self
+            .geometry
+            .modify_vertex(meta_index_id, to_vec3(position))
+        {
+            godot_error!("{error}");
         }
     }
 }
@@ -286,8 +386,12 @@ impl Drop for DynamicMesh {
     }
 }
 
-pub fn decompose_meta_index(meta_index: usize) -> (usize, usize) {
-    let offset = meta_index % 3;
-    let start = meta_index - offset;
-    return (start, offset);
+fn save_file(path: &GString, bytes: &[u8]) -> bool {
+    let Some(mut file) = FileAccess::open(path, ModeFlags::WRITE) else {
+        godot_error!("Couldn't open {path}: {:?}", FileAccess::get_open_error());
+        return false;
+    };
+    file.store_buffer(&PackedByteArray::from(bytes));
+    file.close();
+    true
 }
@@ -1,159 +1,168 @@
-use std::collections::HashMap;
 use std::ops::DerefMut;
 
-use crate::dynamic_mesh::{DynamicMesh, MetaIndexId, decompose_meta_index};
+use crate::convert::{to_godot_color, to_kernel_color, to_vec3, to_vector3};
+use crate::dynamic_mesh::{DynamicMesh, MetaIndexId};
+use godot::classes::FileAccess;
 use godot::prelude::*;
-use serde::{Deserialize, Serialize};
+use rizmo_kernel::io::obj::read_obj;
+use rizmo_kernel::io::ply::read_ply;
+use rizmo_kernel::{
+    Command, CommandError, CommandId, CommandStack, FinishLine, Pivot, Primitive, Smoothing, Space,
+    Subdivision, Target,
+};
 
-#[derive(Serialize, Deserialize, Debug)]
-enum Command {
-    // Selection stack manipulation
-    PushSelection,
-    PopSelection,
+fn to_signal_params(command: &Command) -> (GString, Dictionary) {
+    match command {
+        Command::PushSelection => ("Push Selection".into(), vdict! {}),
+        Command::PopSelection => ("Pop Selection".into(), vdict! {}),
+        Command::MoveFaceSelection => ("Move Face Selection".into(), vdict! {}),
+        Command::MoveEdgeSelection => ("Move Edge Selection".into(), vdict! {}),
+        Command::Translate { delta, space } => (
+            "Translate".into(),
+            vdict! {"delta": to_vector3(*delta), "space": space.name()},
+        ),
+        Command::Scale {
+            factor,
+            pivot,
+            target,
+        } => (
+            "Scale".into(),
+            vdict! {"factor": to_vector3(*factor), "pivot": pivot.name(), "target": target.name()},
+        ),
+        Command::Rotate {
+            axis,
+            angle,
+            pivot,
+            target,
+        } => (
+            "Rotate".into(),
+            vdict! {"axis": to_vector3(*axis), "angle": *angle, "pivot": pivot.name(), "target": target.name()},
+        ),
+        Command::Split(amount) => ("Split".into(), vdict! {"amount": *amount}),
+        Command::Poke(weights) => ("Poke".into(), vdict! {"weights": to_vector3(*weights)}),
+        Command::Pull => ("Pull".into(), vdict! {}),
+        Command::Extrude(distance) => ("Extrude".into(), vdict! {"distance": *distance}),
+        Command::Inset(amount) => ("Inset".into(), vdict! {"amount": *amount}),
+        Command::EdgeCollapse(amount) => ("Edge Collapse".into(), vdict! {"amount": *amount}),
+        Command::FlipEdge => ("Flip Edge".into(), vdict! {}),
+        Command::DeleteFace => ("Delete Face".into(), vdict! {}),
+        Command::FillHole => ("Fill Hole".into(), vdict! {}),
+        Command::Subdivide {
+            levels,
+            scheme,
+            target,
+        } => (
+            "Subdivide".into(),
+            vdict! {"levels": *levels, "scheme": scheme.name(), "target": target.name()},
+        ),
+        Command::Smooth {
+            iterations,
+            factor,
+            mode,
+            target,
+        } => (
+            "Smooth".into(),
+            vdict! {"iterations": *iterations, "factor": *factor, "mode": mode.name(), "target": target.name()},
+        ),
+        Command::Color(color) => ("Color".into(), vdict! {"color": to_godot_color(*color)}),
+    }
+}
 
-    // Moving the selection around relatively
-    MoveFaceSelection,
-    MoveEdgeSelection,
+// The inverse of to_signal_params, so GDScript can hand back what command_executed gave it.
+fn from_signal_params(name: &GString, params: &Dictionary) -> Option<Command> {
+    let command = match name.to_string().as_str() {
+        "Push Selection" => Command::PushSelection,
+        "Pop Selection" => Command::PopSelection,
+        "Move Face Selection" => Command::MoveFaceSelection,
+        "Move Edge Selection" => Command::MoveEdgeSelection,
+        "Translate" => Command::Translate {
+            delta: to_vec3(params.get("delta")?.try_to().ok()?),
+            space: to_space(params.get("space")?).ok()?,
+        },
+        "Scale" => Command::Scale {
+            factor: to_vec3(params.get("factor")?.try_to().ok()?),
+            pivot: to_pivot(params.get("pivot")?).ok()?,
+            target: to_target(params.get("target")?).ok()?,
+        },
+        "Rotate" => Command::Rotate {
+            axis: to_vec3(params.get("axis")?.try_to().ok()?),
+            angle: params.get("angle")?.try_to().ok()?,
+            pivot: to_pivot(params.get("pivot")?).ok()?,
+            target: to_target(params.get("target")?).ok()?,
+        },
+        "Split" => Command::Split(params.get("amount")?.try_to().ok()?),
+        "Poke" => Command::Poke(to_vec3(params.get("weights")?.try_to().ok()?)),
+        "Pull" => Command::Pull,
+        "Extrude" => Command::Extrude(params.get("distance")?.try_to().ok()?),
+        "Inset" => Command::Inset(params.get("amount")?.try_to().ok()?),
+        "Edge Collapse" => Command::EdgeCollapse(params.get("amount")?.try_to().ok()?),
+        "Flip Edge" => Command::FlipEdge,
+        "Delete Face" => Command::DeleteFace,
+        "Fill Hole" => Command::FillHole,
+        "Subdivide" => Command::Subdivide {
+            levels: params.get("levels")?.try_to().ok()?,
+            scheme: to_subdivision(params.get("scheme")?).ok()?,
+            target: to_target(params.get("target")?).ok()?,
+        },
+        "Smooth" => Command::Smooth {
+            iterations: params.get("iterations")?.try_to().ok()?,
+            factor: params.get("factor")?.try_to().ok()?,
+            mode: to_smoothing(params.get("mode")?).ok()?,
+            target: to_target(params.get("target")?).ok()?,
+        },
+        "Color" => Command::Color(to_kernel_color(params.get("color")?.try_to().ok()?)),
+        _ => return None,
+    };
+    Some(command)
+}
 
-    // Model mutations
-    Translate(Vector3),
-    Split(f32),
-    Pull,
+fn to_space(value: Variant) -> Result<Space, ConvertError> {
+    let name: GString = value.try_to()?;
+    Space::from_name(&name.to_string())
+        .ok_or_else(|| ConvertError::new(format!("unknown space \"{name}\"")))
+}
 
-    // Vertex attributes
-    Color(Color),
+fn to_subdivision(value: Variant) -> Result<Subdivision, ConvertError> {
+    let name: GString = value.try_to()?;
+    Subdivision::from_name(&name.to_string())
+        .ok_or_else(|| ConvertError::new(format!("unknown subdivision scheme \"{name}\"")))
 }
 
-impl Command {
-    fn call(&self, mesh: &mut DynamicMesh, selections: &mut Array<MetaIndexId>) {
-        assert!(selections.len() > 0);
-        let selection = selections.back().unwrap();
-        let meta_index = mesh.get_meta_index(selection) as usize;
-        match self {
-            Command::PushSelection => selections.push(selections.back().unwrap()),
-            Command::PopSelection => {
-                selections.pop();
-            }
-            Command::MoveFaceSelection => mesh.traverse_connection(selections.back().unwrap()),
-            Command::MoveEdgeSelection => {
-                assert!(
-                    mesh.tracked_indices
-                        .contains_key(&selections.back().unwrap())
-                );
-                let (start, offset) = decompose_meta_index(meta_index);
-                mesh.tracked_indices
-                    .insert(selection, (start + (offset + 1) % 3) as i32);
-            }
-            Command::Translate(delta) => {
-                mesh.modify_vertex(
-                    selection,
-                    mesh.positions[mesh.indices[meta_index] as usize] + *delta,
-                );
-            }
-            Command::Split(amount) => {
-                assert!(*amount >= 0.0 && *amount <= 1.0);
-
-                // Create new vertex
-                let (start_a, offset_a) = decompose_meta_index(meta_index);
-                let (start_b, offset_b) =
-                    decompose_meta_index(mesh.connections[meta_index] as usize);
-                let first_position: Vector3 =
-                    mesh.positions[mesh.indices[meta_index as usize] as usize];
-                let second_position: Vector3 =
-                    mesh.positions[mesh.indices[start_b + offset_b] as usize];
-                let new_position = (1.0 - amount) * first_position + *amount * second_position;
-                let new_index = mesh.add_vertex(new_position);
-
-                // Add two new faces
-                let face_a = &mesh.indices.as_slice()[start_a..start_a + 3];
-                let face_b = &mesh.indices.as_slice()[start_b..start_b + 3];
-                let conn_a = &mesh.connections.as_slice()[start_a..start_a + 3];
-                let conn_b = &mesh.connections.as_slice()[start_b..start_b + 3];
-                mesh.add_faces(
-                    [
-                        new_index as i32,
-                        face_a[(offset_a + 1) % 3],
-                        face_a[(offset_a + 2) % 3],
-                        new_index as i32,
-                        face_b[(offset_b + 1) % 3],
-                        face_b[(offset_b + 2) % 3],
-                    ],
-                    [
-                        (start_b + offset_b) as i32,
-                        conn_a[(offset_a + 1) % 3],
-                        (start_a + (offset_a + 1) % 3) as i32,
-                        meta_index as i32,
-                        conn_b[(offset_b + 1) % 3],
-                        (start_b + (offset_b + 1) % 3) as i32,
-                    ],
-                );
-                // Update the old faces to use the new vertex
-                mesh.indices[start_a + (offset_a + 1) % 3] = new_index as i32;
-                mesh.indices[start_b + (offset_b + 1) % 3] = new_index as i32;
-            }
-            Command::Pull => {
-                // Create new vertex
-                let new_index = mesh.add_vertex(
-                    mesh.positions
-                        .get(mesh.indices.get(meta_index).unwrap() as usize)
-                        .unwrap(),
-                );
-
-                // Set the old face to use the new index
-                let old_index = mesh.indices[meta_index];
-                mesh.indices[meta_index] = new_index as i32;
-
-                // Add two new faces
-                let (start, offset) = decompose_meta_index(meta_index);
-                let num_indices = mesh.indices.len();
-                mesh.add_faces(
-                    [
-                        old_index,
-                        new_index as i32,
-                        mesh.indices[start + (offset + 2) % 3],
-                        new_index as i32,
-                        old_index as i32,
-                        mesh.indices[start + (offset + 1) % 3],
-                    ],
-                    [
-                        (num_indices + 3) as i32,
-                        (start + (offset + 2) % 3) as i32,
-                        mesh.connections[start + (offset + 2) % 3],
-                        num_indices as i32,
-                        mesh.connections[start + (offset + 1) % 3],
-                        meta_index as i32,
-                    ],
-                );
-            }
-            Command::Color(color) => {
-                mesh.colors[mesh.indices[meta_index] as usize] = *color;
-            }
-        };
-    }
+fn to_smoothing(value: Variant) -> Result<Smoothing, ConvertError> {
+    let name: GString = value.try_to()?;
+    Smoothing::from_name(&name.to_string())
+        .ok_or_else(|| ConvertError::new(format!("unknown smoothing mode \"{name}\"")))
+}
 
-    fn to_signal_params(&self) -> (GString, Dictionary) {
-        match self {
-            Command::PushSelection => ("Push Selection".into(), vdict! {}),
-            Command::PopSelection => ("Pop Selection".into(), vdict! {}),
-            Command::MoveFaceSelection => ("Move Face Selection".into(), vdict! {}),
-            Command::MoveEdgeSelection => ("Move Edge Selection".into(), vdict! {}),
-            Command::Translate(delta) => ("Translate".into(), vdict! {"delta": *delta}),
-            Command::Split(amount) => ("Split".into(), vdict! {"amount": *amount}),
-            Command::Pull => ("Pull".into(), vdict! {}),
-            Command::Color(color) => ("Color".into(), vdict! {"color": *color}),
-        }
+// SpinBoxes hand over whole numbers as floats, so those are rounded rather than refused
+fn to_count(value: Variant) -> Result<u32, ConvertError> {
+    let count = match value.try_to::<i64>() {
+        Ok(count) => count as f64,
+        Err(_) => value.try_to::<f64>()?,
+    };
+    if !count.is_finite() || count < 0.0 {
+        return Err(ConvertError::new(format!("{count} isn't a count")));
     }
+    Ok(count.round().min(u32::MAX as f64) as u32)
 }
 
-type CommandId = i32;
+fn to_pivot(value: Variant) -> Result<Pivot, ConvertError> {
+    let name: GString = value.try_to()?;
+    Pivot::from_name(&name.to_string())
+        .ok_or_else(|| ConvertError::new(format!("unknown pivot \"{name}\"")))
+}
+
+fn to_target(value: Variant) -> Result<Target, ConvertError> {
+    let name: GString = value.try_to()?;
+    Target::from_name(&name.to_string())
+        .ok_or_else(|| ConvertError::new(format!("unknown target \"{name}\"")))
+}
 
 #[derive(GodotClass)]
 #[class(init, base=Node)]
 struct Interpreter {
-    commands: HashMap<CommandId, Command>,
-    index: CommandId,
-    #[var]
-    selections: Array<MetaIndexId>,
+    commands: CommandStack,
+    selections: Vec<MetaIndexId>,
     #[var]
     #[init(val = DynamicMesh::new_alloc())]
     mesh: Gd<DynamicMesh>,
@@ -174,37 +183,134 @@ impl Interpreter {
     #[signal]
     fn command_executed(command_id: CommandId, command_name: GString, params: Dictionary);
 
+    /// `command_id` is -1 when a new command failed and was never added to the stack.
+    #[signal]
+    fn command_failed(command_id: CommandId, message: GString);
+
     #[func]
-    fn reset(&mut self) {
-        self.mesh.bind_mut().deref_mut().clear();
-        self.selections.clear();
-        self.selections
-            .push(self.mesh.bind_mut().deref_mut().track_index(0));
+    fn get_selections(&self) -> Array<MetaIndexId> {
+        self.selections.iter().copied().collect()
+    }
 
-        self.call_commands();
+    #[func]
+    fn reset(&mut self) {
+        let result = self
+            .commands
+            .replay(&mut self.mesh.bind_mut().geometry, &mut self.selections);
         self.mesh.bind_mut().deref_mut().submit_new_geometry();
+        if let Err(error) = result {
+            self.emit_command_failed(error.command_id, &error.error.to_string());
+        }
+    }
+
+    /// Checks the mesh after every command and reports the first command that breaks it through
+    /// command_failed. A new command that would break it is refused instead of added.
+    #[func]
+    fn set_debug_validation(&mut self, enabled: bool) {
+        self.commands.set_validation(enabled);
+        self.reset();
     }
 
     #[func]
     fn undo_command(&mut self, id: CommandId) {
-        self.commands.remove(&id);
+        self.commands.remove(id);
+        self.reset();
+    }
+
+    #[func]
+    fn insert_command_before(
+        &mut self,
+        id: CommandId,
+        command_name: GString,
+        params: Dictionary,
+    ) -> CommandId {
+        let Some(command) = from_signal_params(&command_name, &params) else {
+            godot_error!("Unknown command {command_name} {params}");
+            return -1;
+        };
+        let Some(new_id) = self.commands.insert_before(id, command) else {
+            godot_error!("No command with id {id}");
+            return -1;
+        };
+        self.reset();
+        new_id
+    }
+
+    #[func]
+    fn insert_command_after(
+        &mut self,
+        id: CommandId,
+        command_name: GString,
+        params: Dictionary,
+    ) -> CommandId {
+        let Some(command) = from_signal_params(&command_name, &params) else {
+            godot_error!("Unknown command {command_name} {params}");
+            return -1;
+        };
+        let Some(new_id) = self.commands.insert_after(id, command) else {
+            godot_error!("No command with id {id}");
+            return -1;
+        };
+        self.reset();
+        new_id
+    }
+
+    #[func]
+    fn move_command(&mut self, id: CommandId, position: i32) {
+        if self
+            .commands
+            .move_command(id, position.max(0) as usize)
+            .is_none()
+        {
+            godot_error!("No command with id {id}");
+            return;
+        }
+        self.reset();
+    }
+
+    /// Only commands up to and including `id` are evaluated, and new commands are inserted after it.
+    #[func]
+    fn set_finish_line(&mut self, id: CommandId) {
+        if self
+            .commands
+            .set_finish_line(FinishLine::After(id))
+            .is_none()
+        {
+            godot_error!("No command with id {id}");
+            return;
+        }
+        self.reset();
+    }
+
+    #[func]
+    fn set_finish_line_to_start(&mut self) {
+        self.commands.set_finish_line(FinishLine::Start);
+        self.reset();
+    }
+
+    #[func]
+    fn clear_finish_line(&mut self) {
+        self.commands.set_finish_line(FinishLine::End);
         self.reset();
     }
 
     #[func]
     fn commands_as_json_string(&self) -> GString {
         // Wow 0_0
-        return GString::from(serde_json::to_string(&self.commands).unwrap());
+        GString::from(self.commands.to_json().unwrap())
     }
 
     #[func]
     fn load_commands_from_json_string(&mut self, string: GString) {
-        let commands: Vec<Command> = serde_json::from_str(&string.to_string()).unwrap();
-        for command in commands.into_iter() {
-            let (name, args) = command.to_signal_params();
-            command.call(self.mesh.bind_mut().deref_mut(), &mut self.selections);
-            let new_id = self.get_new_command_id();
-            self.commands.insert(new_id, command);
+        let new_ids = match self.commands.extend_from_json(&string.to_string()) {
+            Ok(new_ids) => new_ids,
+            Err(error) => {
+                godot_error!("Couldn't load the command stack: {error}");
+                return;
+            }
+        };
+        for new_id in new_ids {
+            let (name, args) = to_signal_params(self.commands.get(new_id).unwrap());
             self.to_gd()
                 .signals()
                 .command_executed()
@@ -213,6 +319,140 @@ impl Interpreter {
         self.reset();
     }
 
+    #[func]
+    fn commands_as_script(&self) -> GString {
+        GString::from(self.commands.to_script())
+    }
+
+    #[func]
+    fn load_commands_from_script(&mut self, script: GString) {
+        let new_ids = match self.commands.extend_from_script(&script.to_string()) {
+            Ok(new_ids) => new_ids,
+            Err(error) => {
+                godot_error!("Couldn't load the script at {error}");
+                return;
+            }
+        };
+        for new_id in new_ids {
+            let (name, args) = to_signal_params(self.commands.get(new_id).unwrap());
+            self.to_gd()
+                .signals()
+                .command_executed()
+                .emit(new_id, &name, &args);
+        }
+        self.reset();
+    }
+
+    /// Notes saved along with the commands, such as the author or a description.
+    #[func]
+    fn get_document_metadata(&self) -> Dictionary {
+        let mut metadata = Dictionary::new();
+        for (key, value) in self.commands.metadata() {
+            metadata.set(key.as_str(), value.as_str());
+        }
+        metadata
+    }
+
+    #[func]
+    fn set_document_metadata(&mut self, key: GString, value: GString) {
+        self.commands
+            .metadata_mut()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    /// Starts the model from the mesh at `path` instead of the primitive, replaying the
+    /// commands on top of it. The mesh is saved along with the commands.
+    #[func]
+    fn import_mesh(&mut self, path: GString) -> bool {
+        let bytes = FileAccess::get_file_as_bytes(&path);
+        if bytes.is_empty() {
+            godot_error!("Couldn't read {path}: {:?}", FileAccess::get_open_error());
+            return false;
+        }
+        let result = match path.get_extension().to_lower().to_string().as_str() {
+            "ply" => read_ply(bytes.as_slice()).map_err(|error| error.to_string()),
+            "obj" => read_obj(bytes.as_slice(), true)
+                .map(|(mesh, report)| {
+                    if !report.is_clean() {
+                        godot_warn!("Repaired {path}: {report}");
+                    }
+                    mesh
+                })
+                .map_err(|error| error.to_string()),
+            extension => {
+                godot_error!("Can't import .{extension} files");
+                return false;
+            }
+        };
+        match result {
+            Ok(mesh) => {
+                self.commands.set_base(Some(mesh));
+                self.reset();
+                true
+            }
+            Err(error) => {
+                godot_error!("Couldn't import {path}: {error}");
+                false
+            }
+        }
+    }
+
+    /// Starts the model from the current geometry of `mesh` instead of the primitive, for
+    /// example one made by DynamicMesh.from_mesh.
+    #[func]
+    fn set_base_mesh(&mut self, mesh: Gd<DynamicMesh>) {
+        self.commands.set_base(Some(mesh.bind().geometry.clone()));
+        self.reset();
+    }
+
+    /// Starts the model from a primitive such as "icosphere" or "torus" instead of the tetrahedron
+    /// or an imported mesh, replaying the commands on top of it. `params` overrides the
+    /// primitive's defaults, for example {"subdivisions": 3} for an icosphere.
+    #[func]
+    fn set_primitive(&mut self, name: GString, params: Dictionary) -> bool {
+        let Some(primitive) = Primitive::from_name(&name.to_string()) else {
+            godot_error!("There's no primitive called {name}");
+            return false;
+        };
+        let count = |key: &str, default: u32| {
+            params
+                .get(key)
+                .and_then(|value| value.try_to().ok())
+                .unwrap_or(default)
+        };
+        let primitive = match primitive {
+            Primitive::Icosphere { subdivisions } => Primitive::Icosphere {
+                subdivisions: count("subdivisions", subdivisions),
+            },
+            Primitive::UvSphere { segments, rings } => Primitive::UvSphere {
+                segments: count("segments", segments),
+                rings: count("rings", rings),
+            },
+            Primitive::Cylinder { segments } => Primitive::Cylinder {
+                segments: count("segments", segments),
+            },
+            Primitive::Cone { segments } => Primitive::Cone {
+                segments: count("segments", segments),
+            },
+            Primitive::Torus {
+                segments,
+                sides,
+                thickness,
+            } => Primitive::Torus {
+                segments: count("segments", segments),
+                sides: count("sides", sides),
+                thickness: params
+                    .get("thickness")
+                    .and_then(|value| value.try_to().ok())
+                    .unwrap_or(thickness),
+            },
+            primitive => primitive,
+        };
+        self.commands.set_primitive(primitive);
+        self.reset();
+        true
+    }
+
     ///////////////////////////////////////////////////////////////////////////
     // Command functions
     ///////////////////////////////////////////////////////////////////////////
@@ -233,30 +473,159 @@ impl Interpreter {
         self.add_new_command(Command::MoveEdgeSelection);
     }
     #[func]
-    fn translate(&mut self, delta: Vector3) {
-        self.add_new_command(Command::Translate(delta));
+    fn translate(&mut self, delta: Vector3, space: GString) {
+        let space = match to_space(space.to_variant()) {
+            Ok(space) => space,
+            Err(error) => {
+                self.emit_command_failed(-1, &error.to_string());
+                return;
+            }
+        };
+        self.add_new_command(Command::Translate {
+            delta: to_vec3(delta),
+            space,
+        });
         // TODO: Only update the part that got changed
-        let size = self.mesh.bind_mut().deref_mut().positions.len();
+        let size = self.mesh.bind().geometry.positions.len();
         self.mesh
             .bind_mut()
             .deref_mut()
             .submit_updated_positions(0, size as i32);
     }
     #[func]
+    fn scale(&mut self, factor: Vector3, pivot: GString, target: GString) {
+        let (pivot, target) = match (to_pivot(pivot.to_variant()), to_target(target.to_variant())) {
+            (Ok(pivot), Ok(target)) => (pivot, target),
+            (Err(error), _) | (_, Err(error)) => {
+                self.emit_command_failed(-1, &error.to_string());
+                return;
+            }
+        };
+        self.add_new_command(Command::Scale {
+            factor: to_vec3(factor),
+            pivot,
+            target,
+        });
+        // TODO: Only update the part that got changed
+        self.mesh.bind_mut().deref_mut().submit_new_geometry();
+    }
+    #[func]
+    fn rotate(&mut self, axis: Vector3, angle: f32, pivot: GString, target: GString) {
+        let (pivot, target) = match (to_pivot(pivot.to_variant()), to_target(target.to_variant())) {
+            (Ok(pivot), Ok(target)) => (pivot, target),
+            (Err(error), _) | (_, Err(error)) => {
+                self.emit_command_failed(-1, &error.to_string());
+                return;
+            }
+        };
+        self.add_new_command(Command::Rotate {
+            axis: to_vec3(axis),
+            angle,
+            pivot,
+            target,
+        });
+        // TODO: Only update the part that got changed
+        self.mesh.bind_mut().deref_mut().submit_new_geometry();
+    }
+    #[func]
     fn split(&mut self, amount: f32) {
         self.add_new_command(Command::Split(amount));
         // TODO: Only update the part that got changed
         self.mesh.bind_mut().deref_mut().submit_new_geometry();
     }
     #[func]
+    fn poke(&mut self, weights: Vector3) {
+        self.add_new_command(Command::Poke(to_vec3(weights)));
+        // TODO: Only update the part that got changed
+        self.mesh.bind_mut().deref_mut().submit_new_geometry();
+    }
+    #[func]
     fn pull(&mut self) {
         self.add_new_command(Command::Pull);
         // TODO: Only update the part that got changed
         self.mesh.bind_mut().deref_mut().submit_new_geometry();
     }
     #[func]
+    fn extrude(&mut self, distance: f32) {
+        self.add_new_command(Command::Extrude(distance));
+        // TODO: Only update the part that got changed
+        self.mesh.bind_mut().deref_mut().submit_new_geometry();
+    }
+    #[func]
+    fn inset(&mut self, amount: f32) {
+        self.add_new_command(Command::Inset(amount));
+        // TODO: Only update the part that got changed
+        self.mesh.bind_mut().deref_mut().submit_new_geometry();
+    }
+    #[func]
+    fn edge_collapse(&mut self, amount: f32) {
+        self.add_new_command(Command::EdgeCollapse(amount));
+        // TODO: Only update the part that got changed
+        self.mesh.bind_mut().deref_mut().submit_new_geometry();
+    }
+    #[func]
+    fn flip_edge(&mut self) {
+        self.add_new_command(Command::FlipEdge);
+        // TODO: Only update the part that got changed
+        self.mesh.bind_mut().deref_mut().submit_new_geometry();
+    }
+    #[func]
+    fn delete_face(&mut self) {
+        self.add_new_command(Command::DeleteFace);
+        // TODO: Only update the part that got changed
+        self.mesh.bind_mut().deref_mut().submit_new_geometry();
+    }
+    #[func]
+    fn fill_hole(&mut self) {
+        self.add_new_command(Command::FillHole);
+        // TODO: Only update the part that got changed
+        self.mesh.bind_mut().deref_mut().submit_new_geometry();
+    }
+    #[func]
+    fn subdivide(&mut self, levels: u32, scheme: GString, target: GString) {
+        let (scheme, target) = match (
+            to_subdivision(scheme.to_variant()),
+            to_target(target.to_variant()),
+        ) {
+            (Ok(scheme), Ok(target)) => (scheme, target),
+            (Err(error), _) | (_, Err(error)) => {
+                self.emit_command_failed(-1, &error.to_string());
+                return;
+            }
+        };
+        self.add_new_command(Command::Subdivide {
+            levels,
+            scheme,
+            target,
+        });
+        // TODO: Only update the part that got changed
+        self.mesh.bind_mut().deref_mut().submit_new_geometry();
+    }
+
+    #[func]
+    fn smooth(&mut self, iterations: u32, factor: f32, mode: GString, target: GString) {
+        let (mode, target) = match (
+            to_smoothing(mode.to_variant()),
+            to_target(target.to_variant()),
+        ) {
+            (Ok(mode), Ok(target)) => (mode, target),
+            (Err(error), _) | (_, Err(error)) => {
+                self.emit_command_failed(-1, &error.to_string());
+                return;
+            }
+        };
+        self.add_new_command(Command::Smooth {
+            iterations,
+            factor,
+            mode,
+            target,
+        });
+        // TODO: Only update the part that got changed
+        self.mesh.bind_mut().deref_mut().submit_new_geometry();
+    }
+    #[func]
     fn color(&mut self, color: Color) {
-        self.add_new_command(Command::Color(color));
+        self.add_new_command(Command::Color(to_kernel_color(color)));
         // TODO: Only update the part that got changed
         self.mesh.bind_mut().deref_mut().submit_new_geometry();
     }
@@ -265,44 +634,127 @@ impl Interpreter {
 
     #[func]
     fn update_command(&mut self, id: CommandId, args: VariantArray) {
-        assert!(self.commands.contains_key(&id));
-        let command = self.commands.get(&id).unwrap();
-        match command {
-            Command::Translate(_) => {
-                self.commands
-                    .insert(id, Command::Translate(args.at(0).to()));
-            }
-            Command::Split(_) => {
-                self.commands.insert(id, Command::Split(args.at(0).to()));
-            }
-            Command::Color(_) => {
-                self.commands.insert(id, Command::Color(args.at(0).to()));
+        let Some(command) = self.commands.get_mut(id) else {
+            godot_error!("No command with id {id}");
+            return;
+        };
+        let arg = args.get(0).unwrap_or_default();
+        let updated = match command {
+            Command::Translate { delta, space } => arg.try_to().and_then(|value| {
+                let new_space = to_space(args.get(1).unwrap_or_default())?;
+                *delta = to_vec3(value);
+                *space = new_space;
+                Ok(())
+            }),
+            Command::Scale {
+                factor,
+                pivot,
+                target,
+            } => arg.try_to().and_then(|value| {
+                let new_pivot = to_pivot(args.get(1).unwrap_or_default())?;
+                let new_target = to_target(args.get(2).unwrap_or_default())?;
+                *factor = to_vec3(value);
+                *pivot = new_pivot;
+                *target = new_target;
+                Ok(())
+            }),
+            Command::Rotate {
+                axis,
+                angle,
+                pivot,
+                target,
+            } => arg.try_to().and_then(|value| {
+                let new_angle = args.get(1).unwrap_or_default().try_to()?;
+                let new_pivot = to_pivot(args.get(2).unwrap_or_default())?;
+                let new_target = to_target(args.get(3).unwrap_or_default())?;
+                *axis = to_vec3(value);
+                *angle = new_angle;
+                *pivot = new_pivot;
+                *target = new_target;
+                Ok(())
+            }),
+            Command::Split(amount) => arg.try_to().map(|value| *amount = value),
+            Command::Poke(weights) => arg.try_to().map(|value| *weights = to_vec3(value)),
+            Command::Extrude(distance) => arg.try_to().map(|value| *distance = value),
+            Command::Inset(amount) | Command::EdgeCollapse(amount) => {
+                arg.try_to().map(|value| *amount = value)
             }
-            _ => (),
+            Command::Subdivide {
+                levels,
+                scheme,
+                target,
+            } => to_count(arg).and_then(|value| {
+                let new_scheme = to_subdivision(args.get(1).unwrap_or_default())?;
+                let new_target = to_target(args.get(2).unwrap_or_default())?;
+                *levels = value;
+                *scheme = new_scheme;
+                *target = new_target;
+                Ok(())
+            }),
+            Command::Smooth {
+                iterations,
+                factor,
+                mode,
+                target,
+            } => to_count(arg).and_then(|value| {
+                let new_factor = args.get(1).unwrap_or_default().try_to()?;
+                let new_mode = to_smoothing(args.get(2).unwrap_or_default())?;
+                let new_target = to_target(args.get(3).unwrap_or_default())?;
+                *iterations = value;
+                *factor = new_factor;
+                *mode = new_mode;
+                *target = new_target;
+                Ok(())
+            }),
+            Command::Color(color) => arg.try_to().map(|value| *color = to_kernel_color(value)),
+            _ => Ok(()),
         };
+        if let Err(error) = updated {
+            self.emit_command_failed(id, &error.to_string());
+            return;
+        }
         self.reset();
     }
 
     fn add_new_command(&mut self, command: Command) {
-        let new_id = self.get_new_command_id();
-        command.call(self.mesh.bind_mut().deref_mut(), &mut self.selections);
-        let (name, args) = command.to_signal_params();
-        self.commands.insert(new_id, command);
+        if self.commands.validation_enabled() {
+            // Run on copies so a command that leaves broken topology behind changes nothing
+            let mut geometry = self.mesh.bind().geometry.clone();
+            let mut selections = self.selections.clone();
+            if let Err(error) = command.call(&mut geometry, &mut selections) {
+                self.emit_command_failed(-1, &error.to_string());
+                return;
+            }
+            let report = geometry.validate();
+            if !report.is_valid() {
+                let error = CommandError::InvalidTopology(report);
+                self.emit_command_failed(-1, &error.to_string());
+                return;
+            }
+            self.mesh.bind_mut().geometry = geometry;
+            self.selections = selections;
+        } else {
+            // A failed call leaves the mesh and selections untouched
+            let result = command.call(&mut self.mesh.bind_mut().geometry, &mut self.selections);
+            if let Err(error) = result {
+                self.emit_command_failed(-1, &error.to_string());
+                return;
+            }
+        }
+
+        let (name, args) = to_signal_params(&command);
+        let new_id = self.commands.insert_at_finish_line(command);
         self.to_gd()
             .signals()
             .command_executed()
             .emit(new_id, &name, &args);
     }
 
-    fn get_new_command_id(&mut self) -> CommandId {
-        let index = self.index;
-        self.index += 1;
-        return index;
-    }
-
-    fn call_commands(&mut self) {
-        for command in self.commands.values() {
-            command.call(self.mesh.bind_mut().deref_mut(), &mut self.selections);
-        }
+    fn emit_command_failed(&mut self, command_id: CommandId, message: &str) {
+        godot_error!("Command {command_id} failed: {message}");
+        self.to_gd()
+            .signals()
+            .command_failed()
+            .emit(command_id, &GString::from(message));
     }
 }
@@ -1,5 +1,6 @@
 use godot::prelude::*;
 
+mod convert;
 mod dynamic_mesh;
 mod interpreter;
 